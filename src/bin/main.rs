@@ -2,8 +2,6 @@ use ggez::{self, event, filesystem, GameResult};
 use ggez_breakout;
 use std::path;
 
-use ggez_breakout::save::*;
-
 use std::io::{Read, Write};
 
 use ggez_breakout::game::{WINDOW_HEIGHT, WINDOW_WIDTH};
@@ -19,6 +17,15 @@ fn main() -> GameResult {
     ggez::graphics::set_default_filter(ctx, ggez::graphics::FilterMode::Linear);
     let state = &mut ggez_breakout::game::Game::new(ctx)?;
 
+    // --seed <숫자> 로 난수 시드를 지정할 수 있다.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == "--seed") {
+        match args.get(pos + 1).and_then(|seed| seed.parse::<u64>().ok()) {
+            Some(seed) => state.set_seed(seed),
+            None => println!("--seed requires an unsigned integer"),
+        }
+    }
+
     //파일 저장 테스트
 
     println!("Resource stats:");
@@ -30,8 +37,5 @@ fn main() -> GameResult {
     let bytes = "테스트".as_bytes();
     file.write_all(bytes)?;
 
-    let mut save = Save::new();
-    save.init(ctx);
-
     event::run(ctx, event_loop, state)
 }
@@ -3,15 +3,16 @@
 
 use ggez;
 use ggez::event;
-use ggez::event::KeyCode;
+use ggez::event::{Axis, Button, GamepadId, KeyCode, KeyMods};
 use ggez::graphics;
 use ggez::nalgebra as na;
 use ggez::timer;
 use ggez::{Context, GameResult};
 
+use crate::input::{Action, InputMap};
 use crate::reg::Reg;
 use crate::states;
-use crate::states::StateResult;
+use crate::states::{StateResult, States};
 
 use std::path::Path;
 
@@ -46,7 +47,9 @@ impl Game {
         let mut reg = Reg::new();
         reg.init_sprite(ctx, Path::new("/breakout.png"));
         reg.init_heart(ctx, Path::new("/hearts.png"));
-        let init_state = states::InitState::new(ctx, &mut reg);
+        reg.input = InputMap::load(ctx);
+        let mut init_state = states::InitState::new();
+        init_state.on_enter(ctx, &mut reg);
 
         let buffer = ggez::graphics::Canvas::new(
             ctx,
@@ -63,6 +66,143 @@ impl Game {
         };
         Ok(s)
     }
+
+    /// 게임에서 사용할 난수 시드를 지정한다.
+    /// 같은 시드로 시작한 게임은 같은 레벨 배치와 공 발사를 재현한다.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - 난수 시드
+    ///
+    pub fn set_seed(&mut self, seed: u64) {
+        self.reg.set_seed(seed);
+    }
+
+    /// state의 update 결과에 따라 stack을 바꾸고,
+    /// 해당하는 state의 lifecycle hook을 호출한다.
+    fn transition(&mut self, ctx: &mut Context, result: StateResult) {
+        let reg = &mut self.reg;
+        let quit = apply_transition(&mut self.states, result, |state, hook| match hook {
+            Hook::Enter => state.on_enter(ctx, reg),
+            Hook::Exit => state.on_exit(ctx, reg),
+            Hook::Pause => state.on_pause(ctx, reg),
+            Hook::Resume => state.on_resume(ctx, reg),
+        });
+
+        if quit {
+            ggez::event::quit(ctx);
+        }
+    }
+}
+
+/// state의 lifecycle hook
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Hook {
+    Enter,
+    Exit,
+    Pause,
+    Resume,
+}
+
+/// StateResult에 따라 stack을 바꾸고, 불러야 할 hook을 차례로 call에 넘긴다.
+/// 게임을 종료해야 하면 true를 반환한다.
+fn apply_transition<F>(states: &mut Vec<Box<dyn States>>, result: StateResult, mut call: F) -> bool
+where
+    F: FnMut(&mut dyn States, Hook),
+{
+    // state를 stack에 넣고 on_enter 를 호출한다.
+    let push = |states: &mut Vec<Box<dyn States>>, mut state: Box<dyn States>, call: &mut F| {
+        call(state.as_mut(), Hook::Enter);
+        states.push(state);
+    };
+    // 맨 위 state를 빼고 on_exit 를 호출한다.
+    let pop = |states: &mut Vec<Box<dyn States>>, call: &mut F| {
+        if let Some(mut state) = states.pop() {
+            println!("state: exit {}", state.name());
+            call(state.as_mut(), Hook::Exit);
+        }
+    };
+    // 새로 맨 위가 된 state의 on_resume 을 호출한다.
+    let resume = |states: &mut Vec<Box<dyn States>>, call: &mut F| {
+        if let Some(state) = states.last_mut() {
+            call(state.as_mut(), Hook::Resume);
+        }
+    };
+
+    match result {
+        // 새로운 State를 생성하고 해당 State로 수행권한을 넘긴다.
+        // 기존의 State는 멈춘 채로 남는다.
+        StateResult::PushState(s) => {
+            println!("state: push {}", s.name());
+            if let Some(current_state) = states.last_mut() {
+                call(current_state.as_mut(), Hook::Pause);
+            }
+            push(states, s, &mut call);
+        }
+        // 기존의 State를 삭제하고, 이전 State로 이전한다.
+        StateResult::PopState => {
+            println!("state: pop");
+            pop(states, &mut call);
+            resume(states, &mut call);
+        }
+        // 기존의 state를 삭제하고 신규 State로 이전한다.
+        StateResult::Trans(s) => {
+            println!("state: trans {}", s.name());
+            pop(states, &mut call);
+            push(states, s, &mut call);
+        }
+        // 모든 state를 삭제하고 신규 State로 다시 시작한다.
+        StateResult::Reset(s) => {
+            println!("state: reset {}", s.name());
+            while !states.is_empty() {
+                pop(states, &mut call);
+            }
+            push(states, s, &mut call);
+        }
+        // state를 n개 삭제한다.
+        // 0개이거나 stack이 모두 비게 된다면 아무것도 하지 않는다.
+        StateResult::PopN(n) => {
+            if n == 0 || n >= states.len() {
+                println!(
+                    "state: pop {} ignored, stack has {} states",
+                    n,
+                    states.len()
+                );
+            } else {
+                println!("state: pop {}", n);
+                for _ in 0..n {
+                    pop(states, &mut call);
+                }
+                resume(states, &mut call);
+            }
+        }
+        // 이름이 같은 state가 맨 위가 될 때까지 삭제한다.
+        // 해당하는 state가 없거나 이미 맨 위라면 아무것도 하지 않는다.
+        StateResult::PopTo(name) => match states.iter().rposition(|state| state.name() == name) {
+            Some(index) if index + 1 == states.len() => {
+                println!("state: pop to {} ignored, already on top", name)
+            }
+            Some(index) => {
+                println!("state: pop to {}", name);
+                while states.len() > index + 1 {
+                    pop(states, &mut call);
+                }
+                resume(states, &mut call);
+            }
+            None => println!("state: pop to {} failed, no such state", name),
+        },
+        // 모든 state의 on_exit 을 불러 정리할 기회를 주고 종료한다.
+        StateResult::Quit => {
+            println!("state: quit");
+            while !states.is_empty() {
+                pop(states, &mut call);
+            }
+            return true;
+        }
+        StateResult::Void => (),
+    }
+
+    false
 }
 
 impl event::EventHandler for Game {
@@ -72,37 +212,28 @@ impl event::EventHandler for Game {
     /// * `ctx` - Context 객체
     ///
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        // 닫기가 눌러지면 게임 종료한다.
-        if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Escape) {
-            ggez::event::quit(ctx);
-        }
-
         // dt(delta) 얻어오기
         // FPS를 60frame per seconds 로 함
+        // 모든 속도는 초당 단위이므로 이 값을 바꿔도 게임 속도는 같다.
         const DESIRED_FPS: u32 = 60;
 
         while timer::check_update_time(ctx, DESIRED_FPS) {
             let dt = 1.0 / (DESIRED_FPS as f32);
 
+            // 매 틱마다 입력 상태를 갱신한다.
+            self.reg.input.update(ctx);
+
+            // 닫기가 눌러지면 state들을 정리하고 게임 종료한다.
+            if self.reg.input.just_pressed(Action::Quit) {
+                self.transition(ctx, StateResult::Quit);
+                break;
+            }
+
             // 현재 states 값을 얻어와 해당 states의 update 를 실행한다.
             match self.states.last_mut() {
                 Some(current_state) => {
-                    match current_state.update(ctx, &mut self.reg, dt) {
-                        // 새로운 State를 생성하고 해당 State로 수행권한을 넘긴다.
-                        StateResult::PushState(s) => self.states.push(s),
-                        // 기존의 State를 삭제하고, 이전 State로 이전한다.
-                        StateResult::PopState => {
-                            self.states.pop();
-                            ()
-                        }
-                        // 기존의 state를 삭제하고 신규 State로 이전한다.
-                        StateResult::Trans(s) => {
-                            self.states.pop();
-                            self.states.push(s);
-                            ()
-                        }
-                        _ => (),
-                    }
+                    let result = current_state.update(ctx, &mut self.reg, dt);
+                    self.transition(ctx, result);
                 }
                 // 수행할 수 있는 state가 없다면 게임은 종료한다.
                 None => {
@@ -120,6 +251,34 @@ impl event::EventHandler for Game {
         Ok(())
     }
 
+    /// 키 입력은 InputMap이 매 틱마다 읽는다.
+    /// ggez 기본 동작인 Escape 종료를 막아, 종료도 Quit 동작으로만 한다.
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+    }
+
+    /// 창을 닫으면 Quit을 누른 것처럼 state들을 정리하고 종료한다.
+    fn quit_event(&mut self, ctx: &mut Context) -> bool {
+        self.transition(ctx, StateResult::Quit);
+        false
+    }
+
+    /// 게임패드 버튼이 눌리면 해당 게임패드를 입력 장치로 등록한다.
+    /// 게임 중에 연결된 게임패드도 이렇게 바로 쓸 수 있다.
+    fn gamepad_button_down_event(&mut self, _ctx: &mut Context, _btn: Button, id: GamepadId) {
+        self.reg.input.add_gamepad(id);
+    }
+
+    /// 게임패드 축이 움직이면 해당 게임패드를 입력 장치로 등록한다.
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, _axis: Axis, _value: f32, id: GamepadId) {
+        self.reg.input.add_gamepad(id);
+    }
+
     /// Game의 매프레임마다 Double Buffering 을 통해
     /// 화면에 그림을 그린다.
     ///
@@ -139,32 +298,187 @@ impl event::EventHandler for Game {
         // Canvas에 이미지를 그리도록 변경(double buffering)
         graphics::set_canvas(ctx, Some(&self.buffer));
 
-        // 현재 states 값을 얻어와 해당 states의 render 를 실행한다.
-        // 해당하는 renderings 은 buffer 저장된다.
+        // 맨 위에서부터 내려가며 처음으로 만나는 overlay가 아닌 state를 찾는다.
+        // 그 state부터 맨 위 state까지 차례로 render 하여 buffer에 겹쳐 그린다.
+        // update는 맨 위 state만 받으므로 아래 state들은 멈춘 화면으로 남는다.
+        let start = self
+            .states
+            .iter()
+            .rposition(|state| !state.is_overlay())
+            .unwrap_or(0);
 
-        match self.states.last_mut() {
-            Some(current_state) => {
-                current_state.render(ctx, &mut self.reg, &mut self.buffer);
+        if start < self.states.len() {
+            for state in self.states[start..].iter_mut() {
+                graphics::set_canvas(ctx, Some(&self.buffer));
+                state.render(ctx, &mut self.reg, &mut self.buffer);
+            }
 
-                // 이제 메인 윈도우에 그림
-                graphics::set_canvas(ctx, None);
+            // 이제 메인 윈도우에 그림
+            graphics::set_canvas(ctx, None);
 
-                // canvas buffer를 윈도우에 출력
-                graphics::draw(
-                    ctx,
-                    &self.buffer,
-                    graphics::DrawParam::new()
-                        .dest(dest_point)
-                        .src(graphics::Rect::new(0., 0., 1., 1.)),
-                )?;
-            }
-            None => (),
+            // canvas buffer를 윈도우에 출력
+            graphics::draw(
+                ctx,
+                &self.buffer,
+                graphics::DrawParam::new()
+                    .dest(dest_point)
+                    .src(graphics::Rect::new(0., 0., 1., 1.)),
+            )?;
         }
 
-        // 게임이 일시 정지이면 화면에 일시 정지를 출력한다.
-
         graphics::present(ctx)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// hook에서 아무것도 하지 않는 state
+    struct Stub(&'static str);
+
+    impl States for Stub {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn update(&mut self, _ctx: &mut Context, _reg: &mut Reg, _dt: f32) -> StateResult {
+            StateResult::Void
+        }
+
+        fn render(
+            &mut self,
+            _ctx: &mut Context,
+            _reg: &mut Reg,
+            _buffer: &mut graphics::Canvas,
+        ) -> StateResult {
+            StateResult::Void
+        }
+    }
+
+    fn stack(names: &[&'static str]) -> Vec<Box<dyn States>> {
+        names
+            .iter()
+            .map(|name| Box::new(Stub(name)) as Box<dyn States>)
+            .collect()
+    }
+
+    fn names(states: &[Box<dyn States>]) -> Vec<&str> {
+        states.iter().map(|state| state.name()).collect()
+    }
+
+    /// 전이를 적용하고 종료 여부와 불린 hook 목록을 반환한다.
+    fn run(states: &mut Vec<Box<dyn States>>, result: StateResult) -> (bool, Vec<(String, Hook)>) {
+        let mut hooks = vec![];
+        let quit = apply_transition(states, result, |state, hook| {
+            hooks.push((state.name().to_owned(), hook))
+        });
+        (quit, hooks)
+    }
+
+    fn hooks(expected: &[(&str, Hook)]) -> Vec<(String, Hook)> {
+        expected
+            .iter()
+            .map(|(name, hook)| (name.to_string(), *hook))
+            .collect()
+    }
+
+    #[test]
+    fn push_pauses_the_current_state() {
+        let mut states = stack(&["a"]);
+        let (quit, called) = run(&mut states, StateResult::PushState(Box::new(Stub("b"))));
+
+        assert!(!quit);
+        assert_eq!(called, hooks(&[("a", Hook::Pause), ("b", Hook::Enter)]));
+        assert_eq!(names(&states), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn pop_resumes_the_state_below() {
+        let mut states = stack(&["a", "b"]);
+        let (_, called) = run(&mut states, StateResult::PopState);
+
+        assert_eq!(called, hooks(&[("b", Hook::Exit), ("a", Hook::Resume)]));
+        assert_eq!(names(&states), vec!["a"]);
+    }
+
+    #[test]
+    fn trans_replaces_the_top_state() {
+        let mut states = stack(&["a", "b"]);
+        let (_, called) = run(&mut states, StateResult::Trans(Box::new(Stub("c"))));
+
+        assert_eq!(called, hooks(&[("b", Hook::Exit), ("c", Hook::Enter)]));
+        assert_eq!(names(&states), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn reset_exits_every_state_from_the_top() {
+        let mut states = stack(&["a", "b"]);
+        let (_, called) = run(&mut states, StateResult::Reset(Box::new(Stub("c"))));
+
+        assert_eq!(
+            called,
+            hooks(&[("b", Hook::Exit), ("a", Hook::Exit), ("c", Hook::Enter)])
+        );
+        assert_eq!(names(&states), vec!["c"]);
+    }
+
+    #[test]
+    fn pop_n_pops_and_resumes() {
+        let mut states = stack(&["a", "b", "c"]);
+        let (_, called) = run(&mut states, StateResult::PopN(2));
+
+        assert_eq!(
+            called,
+            hooks(&[("c", Hook::Exit), ("b", Hook::Exit), ("a", Hook::Resume)])
+        );
+        assert_eq!(names(&states), vec!["a"]);
+    }
+
+    #[test]
+    fn pop_n_out_of_range_is_ignored() {
+        for n in [0, 2, 5].iter() {
+            let mut states = stack(&["a", "b"]);
+            let (quit, called) = run(&mut states, StateResult::PopN(*n));
+
+            assert!(!quit);
+            assert!(called.is_empty(), "PopN({})", n);
+            assert_eq!(names(&states), vec!["a", "b"]);
+        }
+    }
+
+    #[test]
+    fn pop_to_stops_at_the_named_state() {
+        let mut states = stack(&["a", "b", "c"]);
+        let (_, called) = run(&mut states, StateResult::PopTo("a".to_owned()));
+
+        assert_eq!(
+            called,
+            hooks(&[("c", Hook::Exit), ("b", Hook::Exit), ("a", Hook::Resume)])
+        );
+        assert_eq!(names(&states), vec!["a"]);
+    }
+
+    #[test]
+    fn pop_to_missing_or_top_state_is_ignored() {
+        for name in ["x", "b"].iter() {
+            let mut states = stack(&["a", "b"]);
+            let (_, called) = run(&mut states, StateResult::PopTo(name.to_string()));
+
+            assert!(called.is_empty(), "PopTo({})", name);
+            assert_eq!(names(&states), vec!["a", "b"]);
+        }
+    }
+
+    #[test]
+    fn quit_exits_every_state() {
+        let mut states = stack(&["a", "b"]);
+        let (quit, called) = run(&mut states, StateResult::Quit);
+
+        assert!(quit);
+        assert_eq!(called, hooks(&[("b", Hook::Exit), ("a", Hook::Exit)]));
+        assert!(states.is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! 균일 격자 broadphase
+//! 물체를 일정한 크기의 칸에 나누어 넣어두고,
+//! 주어진 영역과 겹치는 칸에 들어있는 물체만 충돌 후보로 돌려준다.
+
+use crate::objects::Object;
+use std::collections::HashMap;
+
+// 칸 범위 (최소 x, 최소 y, 최대 x, 최대 y), 최대값도 포함한다.
+type CellRange = (i32, i32, i32, i32);
+
+pub struct SpatialGrid {
+    cell_width: f32,
+    cell_height: f32,
+    // 칸 -> 그 칸에 걸친 물체 번호
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    // 물체 번호 -> 물체가 걸친 칸 범위
+    ranges: HashMap<usize, CellRange>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_width: f32, cell_height: f32) -> SpatialGrid {
+        SpatialGrid {
+            cell_width,
+            cell_height,
+            cells: HashMap::new(),
+            ranges: HashMap::new(),
+        }
+    }
+
+    /// 모든 물체를 지운다.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.ranges.clear();
+    }
+
+    /// 목록을 모두 다시 넣는다. 물체 번호는 목록의 순서이다.
+    /// filter가 false인 물체는 넣지 않는다.
+    pub fn rebuild<T: Object>(&mut self, objects: &[T], filter: impl Fn(&T) -> bool) {
+        self.clear();
+        for (index, object) in objects.iter().enumerate() {
+            if filter(object) {
+                self.insert(index, object.get_xywh());
+            }
+        }
+    }
+
+    /// 물체를 넣는다. 이미 있는 번호라면 새 위치로 옮긴다.
+    pub fn insert(&mut self, index: usize, xywh: (f32, f32, f32, f32)) {
+        self.remove(index);
+
+        let range = self.cell_range(xywh);
+        for_each_cell(range, |cell| {
+            self.cells.entry(cell).or_default().push(index)
+        });
+        self.ranges.insert(index, range);
+    }
+
+    /// 물체가 움직였을 때 위치를 갱신한다.
+    pub fn update(&mut self, index: usize, xywh: (f32, f32, f32, f32)) {
+        if self.ranges.get(&index) != Some(&self.cell_range(xywh)) {
+            self.insert(index, xywh);
+        }
+    }
+
+    /// 물체를 뺀다.
+    pub fn remove(&mut self, index: usize) {
+        let range = match self.ranges.remove(&index) {
+            Some(range) => range,
+            None => return,
+        };
+
+        for_each_cell(range, |cell| {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|item| *item != index);
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        });
+    }
+
+    /// 영역과 겹치는 칸에 들어있는 물체 번호 (오름차순, 중복 없음)
+    pub fn query(&self, xywh: (f32, f32, f32, f32)) -> Vec<usize> {
+        let mut result = Vec::<usize>::new();
+
+        for_each_cell(self.cell_range(xywh), |cell| {
+            if let Some(items) = self.cells.get(&cell) {
+                result.extend(items);
+            }
+        });
+
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    fn cell_range(&self, (x, y, w, h): (f32, f32, f32, f32)) -> CellRange {
+        (
+            (x / self.cell_width).floor() as i32,
+            (y / self.cell_height).floor() as i32,
+            ((x + w) / self.cell_width).floor() as i32,
+            ((y + h) / self.cell_height).floor() as i32,
+        )
+    }
+}
+
+fn for_each_cell<F: FnMut((i32, i32))>(range: CellRange, mut f: F) {
+    let (min_x, min_y, max_x, max_y) = range;
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            f((x, y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 블럭 하나 크기의 칸
+    fn grid() -> SpatialGrid {
+        SpatialGrid::new(32., 16.)
+    }
+
+    #[test]
+    fn query_finds_objects_in_overlapping_cells() {
+        let mut grid = grid();
+        grid.insert(0, (0., 0., 30., 14.));
+        grid.insert(1, (100., 100., 30., 14.));
+
+        assert_eq!(grid.query((4., 4., 8., 8.)), vec![0]);
+        assert_eq!(grid.query((104., 104., 8., 8.)), vec![1]);
+        assert_eq!(grid.query((300., 200., 8., 8.)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn object_spanning_several_cells_is_found_from_each() {
+        let mut grid = grid();
+        // (0, 0) ~ (1, 1) 네 칸에 걸친다.
+        grid.insert(7, (16., 8., 32., 16.));
+
+        assert_eq!(grid.ranges[&7], (0, 0, 1, 1));
+        for cell in [(0., 0.), (40., 0.), (0., 20.), (40., 20.)].iter() {
+            assert_eq!(grid.query((cell.0, cell.1, 1., 1.)), vec![7], "{:?}", cell);
+        }
+        assert_eq!(grid.query((70., 0., 1., 1.)), Vec::<usize>::new());
+
+        // 여러 칸에서 찾아도 한 번만 돌려준다.
+        assert_eq!(grid.query((0., 0., 64., 32.)), vec![7]);
+    }
+
+    #[test]
+    fn query_is_sorted_and_deduplicated() {
+        let mut grid = grid();
+        grid.insert(5, (0., 0., 64., 32.));
+        grid.insert(2, (10., 10., 40., 4.));
+        grid.insert(9, (33., 17., 4., 4.));
+
+        assert_eq!(grid.query((0., 0., 64., 32.)), vec![2, 5, 9]);
+    }
+
+    #[test]
+    fn remove_clears_every_cell() {
+        let mut grid = grid();
+        grid.insert(3, (16., 8., 32., 16.));
+        grid.insert(4, (40., 20., 4., 4.));
+
+        grid.remove(3);
+        assert_eq!(grid.query((0., 0., 64., 32.)), vec![4]);
+
+        grid.remove(4);
+        assert!(grid.cells.is_empty());
+        assert!(grid.ranges.is_empty());
+
+        // 없는 번호를 빼도 괜찮다.
+        grid.remove(4);
+    }
+
+    #[test]
+    fn update_moves_object_to_new_cells() {
+        let mut grid = grid();
+        grid.insert(0, (0., 0., 30., 14.));
+
+        // 같은 칸 안에서 움직이면 그대로
+        grid.update(0, (1., 1., 30., 14.));
+        assert_eq!(grid.ranges[&0], (0, 0, 0, 0));
+
+        grid.update(0, (192., 96., 30., 14.));
+        assert_eq!(grid.query((4., 4., 8., 8.)), Vec::<usize>::new());
+        assert_eq!(grid.query((200., 100., 8., 8.)), vec![0]);
+        assert_eq!(grid.cells.len(), 1);
+    }
+
+    #[test]
+    fn insert_twice_replaces_old_position() {
+        let mut grid = grid();
+        grid.insert(0, (0., 0., 30., 14.));
+        grid.insert(0, (100., 0., 30., 14.));
+
+        assert_eq!(grid.query((4., 4., 8., 8.)), Vec::<usize>::new());
+        assert_eq!(grid.query((104., 4., 8., 8.)), vec![0]);
+    }
+}
@@ -0,0 +1,1027 @@
+//! 입력 매핑
+//! 게임은 물리적인 키 대신 추상적인 동작(Action)으로 입력을 확인한다.
+//! 하나의 동작에는 여러 입력을 연결할 수 있으며,
+//! InputMap은 매 틱마다 갱신되어 눌림, 방금 눌림, 방금 떼어짐을 알려준다.
+//!
+//! 키 설정은 ggez 사용자 설정 디렉토리의 파일에 저장한다.
+//!
+//! ```text
+//! # 주석
+//! move_left = Left, A
+//! serve = Space
+//! ```
+//!
+//! 게임패드 버튼은 `Pad:South`, 아날로그 축은 방향을 붙여 `Pad:LeftStickX-` 처럼 쓴다.
+//! 마우스 버튼은 `Mouse:Left` 처럼 쓴다.
+//!
+//! 동작 외에 마우스로 paddle을 움직이는 설정도 같은 파일에 둔다.
+//!
+//! ```text
+//! mouse_paddle = on
+//! mouse_speed = 600
+//! ```
+//!
+//! 알 수 없는 동작이나 키, 같은 화면에서 쓰이는 다른 동작과 겹치는 키는
+//! 경고를 출력하고 무시하며, 연결된 키가 하나도 남지 않은 동작은 기본 설정을 사용한다.
+
+use crate::game;
+use ggez::error::GameResult;
+use ggez::event::{Axis, Button, MouseButton};
+use ggez::filesystem;
+use ggez::graphics;
+use ggez::input::gamepad::{self, GamepadId};
+use ggez::input::keyboard::{self, KeyCode};
+use ggez::input::mouse;
+use ggez::Context;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+pub const BINDINGS_FILE: &str = "/controls.txt";
+
+// 이 값 이하로 기울인 아날로그 축은 무시한다.
+pub const AXIS_DEADZONE: f32 = 0.2;
+// 아날로그 축을 이 값 이상 기울이면 눌린 것으로 본다.
+pub const AXIS_PRESS: f32 = 0.5;
+
+// 설정 파일에서 게임패드, 마우스 입력 앞에 붙이는 이름
+const PAD_PREFIX: &str = "Pad:";
+const MOUSE_PREFIX: &str = "Mouse:";
+
+// 마우스를 따라가는 paddle의 기본 최고 속도 (초당 픽셀)
+pub const MOUSE_SPEED: f32 = 600.;
+
+/// 설정 파일에 쓸 수 있는 마우스 버튼
+const MOUSE_BUTTONS: [(MouseButton, &str); 3] = [
+    (MouseButton::Left, "Left"),
+    (MouseButton::Right, "Right"),
+    (MouseButton::Middle, "Middle"),
+];
+
+/// 설정 파일에 쓸 수 있는 게임패드 버튼
+const BUTTONS: [(Button, &str); 19] = [
+    (Button::South, "South"),
+    (Button::East, "East"),
+    (Button::North, "North"),
+    (Button::West, "West"),
+    (Button::C, "C"),
+    (Button::Z, "Z"),
+    (Button::LeftTrigger, "LeftTrigger"),
+    (Button::LeftTrigger2, "LeftTrigger2"),
+    (Button::RightTrigger, "RightTrigger"),
+    (Button::RightTrigger2, "RightTrigger2"),
+    (Button::Select, "Select"),
+    (Button::Start, "Start"),
+    (Button::Mode, "Mode"),
+    (Button::LeftThumb, "LeftThumb"),
+    (Button::RightThumb, "RightThumb"),
+    (Button::DPadUp, "DPadUp"),
+    (Button::DPadDown, "DPadDown"),
+    (Button::DPadLeft, "DPadLeft"),
+    (Button::DPadRight, "DPadRight"),
+];
+
+/// 설정 파일에 쓸 수 있는 게임패드 축
+const AXES: [(Axis, &str); 8] = [
+    (Axis::LeftStickX, "LeftStickX"),
+    (Axis::LeftStickY, "LeftStickY"),
+    (Axis::LeftZ, "LeftZ"),
+    (Axis::RightStickX, "RightStickX"),
+    (Axis::RightStickY, "RightStickY"),
+    (Axis::RightZ, "RightZ"),
+    (Axis::DPadX, "DPadX"),
+    (Axis::DPadY, "DPadY"),
+];
+
+/// 설정 파일에 쓸 수 있는 키
+const KEYS: [(KeyCode, &str); 87] = [
+    (KeyCode::Key1, "Key1"),
+    (KeyCode::Key2, "Key2"),
+    (KeyCode::Key3, "Key3"),
+    (KeyCode::Key4, "Key4"),
+    (KeyCode::Key5, "Key5"),
+    (KeyCode::Key6, "Key6"),
+    (KeyCode::Key7, "Key7"),
+    (KeyCode::Key8, "Key8"),
+    (KeyCode::Key9, "Key9"),
+    (KeyCode::Key0, "Key0"),
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Escape, "Escape"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::F12, "F12"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Back, "Back"),
+    (KeyCode::Return, "Return"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Numpad0, "Numpad0"),
+    (KeyCode::Numpad1, "Numpad1"),
+    (KeyCode::Numpad2, "Numpad2"),
+    (KeyCode::Numpad3, "Numpad3"),
+    (KeyCode::Numpad4, "Numpad4"),
+    (KeyCode::Numpad5, "Numpad5"),
+    (KeyCode::Numpad6, "Numpad6"),
+    (KeyCode::Numpad7, "Numpad7"),
+    (KeyCode::Numpad8, "Numpad8"),
+    (KeyCode::Numpad9, "Numpad9"),
+    (KeyCode::NumpadEnter, "NumpadEnter"),
+    (KeyCode::LShift, "LShift"),
+    (KeyCode::RShift, "RShift"),
+    (KeyCode::LControl, "LControl"),
+    (KeyCode::RControl, "RControl"),
+    (KeyCode::LAlt, "LAlt"),
+    (KeyCode::RAlt, "RAlt"),
+    (KeyCode::Comma, "Comma"),
+    (KeyCode::Period, "Period"),
+    (KeyCode::Slash, "Slash"),
+    (KeyCode::Semicolon, "Semicolon"),
+    (KeyCode::Apostrophe, "Apostrophe"),
+    (KeyCode::Minus, "Minus"),
+    (KeyCode::Equals, "Equals"),
+];
+
+/// 표에서 입력의 이름을 찾는다. 표에 없는 입력은 설정 파일에 쓸 수 없다.
+fn name_in<T: PartialEq>(table: &[(T, &'static str)], value: T) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map(|(_, name)| *name)
+}
+
+/// 표에서 이름에 해당하는 입력을 찾는다. 대소문자는 구분하지 않는다.
+fn find_in<T: Copy>(table: &[(T, &str)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|(v, _)| *v)
+}
+
+/// 입력을 읽는 화면
+/// 한 화면에서 함께 읽는 동작끼리는 같은 입력을 나눠 쓸 수 없다.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Screen {
+    Title,
+    Controls,
+    PaddleSelect,
+    Play,
+    Pause,
+    // 레벨 클리어, 게임 오버
+    Result,
+}
+
+impl Screen {
+    pub const ALL: [Screen; 6] = [
+        Screen::Title,
+        Screen::Controls,
+        Screen::PaddleSelect,
+        Screen::Play,
+        Screen::Pause,
+        Screen::Result,
+    ];
+
+    /// 이 화면의 state가 읽는 동작
+    /// Quit은 Game이 모든 화면에서 읽으므로 여기에 넣지 않는다.
+    pub fn actions(self) -> &'static [Action] {
+        match self {
+            Screen::Title => &[Action::MenuUp, Action::MenuDown, Action::Confirm],
+            Screen::Controls => &[
+                Action::MenuUp,
+                Action::MenuDown,
+                Action::Confirm,
+                Action::Back,
+            ],
+            Screen::PaddleSelect => &[
+                Action::MoveLeft,
+                Action::MoveRight,
+                Action::Confirm,
+                Action::Back,
+            ],
+            Screen::Play => &[
+                Action::MoveLeft,
+                Action::MoveRight,
+                Action::AimLeft,
+                Action::AimRight,
+                Action::Serve,
+                Action::Pause,
+                Action::Back,
+            ],
+            Screen::Pause => &[Action::Confirm, Action::Pause],
+            Screen::Result => &[Action::Confirm],
+        }
+    }
+}
+
+/// 게임에서 사용하는 동작
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    AimLeft,
+    AimRight,
+    Serve,
+    Pause,
+    Confirm,
+    Back,
+    MenuUp,
+    MenuDown,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::AimLeft,
+        Action::AimRight,
+        Action::Serve,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::Quit,
+    ];
+
+    /// 설정 파일에 쓰는 이름
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::AimLeft => "aim_left",
+            Action::AimRight => "aim_right",
+            Action::Serve => "serve",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// 화면에 표시하는 이름
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::AimLeft => "Aim Left",
+            Action::AimRight => "Aim Right",
+            Action::Serve => "Serve",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::MenuUp => "Menu Up",
+            Action::MenuDown => "Menu Down",
+            Action::Quit => "Quit",
+        }
+    }
+
+    /// 두 동작이 같은 입력을 나눠 쓸 수 없는지 확인
+    /// 한 화면에서 함께 읽히거나, 어느 한쪽이 Quit이면 겹친다.
+    pub fn overlaps(self, other: Action) -> bool {
+        self == Action::Quit
+            || other == Action::Quit
+            || Screen::ALL
+                .iter()
+                .any(|screen| screen.actions().contains(&self) && screen.actions().contains(&other))
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .find(|action| action.name() == name)
+            .copied()
+    }
+}
+
+/// 동작에 연결되는 물리적인 입력
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(Button),
+    // 아날로그 축을 +, - 방향으로 기울임
+    AxisPositive(Axis),
+    AxisNegative(Axis),
+    Mouse(MouseButton),
+}
+
+/// 입력 장치 종류
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Device {
+    Keyboard,
+    Gamepad,
+    Mouse,
+}
+
+impl Binding {
+    /// 입력 이름 (설정 파일과 화면에 쓰인다.)
+    /// 설정 파일에 쓸 수 없는 입력이면 None
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Binding::Key(key) => name_in(&KEYS, *key).map(str::to_owned),
+            Binding::Button(button) => {
+                name_in(&BUTTONS, *button).map(|name| format!("{}{}", PAD_PREFIX, name))
+            }
+            Binding::AxisPositive(axis) => {
+                name_in(&AXES, *axis).map(|name| format!("{}{}+", PAD_PREFIX, name))
+            }
+            Binding::AxisNegative(axis) => {
+                name_in(&AXES, *axis).map(|name| format!("{}{}-", PAD_PREFIX, name))
+            }
+            Binding::Mouse(button) => {
+                name_in(&MOUSE_BUTTONS, *button).map(|name| format!("{}{}", MOUSE_PREFIX, name))
+            }
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Binding> {
+        if let Some(name) = name.strip_prefix(MOUSE_PREFIX) {
+            return find_in(&MOUSE_BUTTONS, name).map(Binding::Mouse);
+        }
+
+        let name = match name.strip_prefix(PAD_PREFIX) {
+            Some(name) => name,
+            None => return find_in(&KEYS, name).map(Binding::Key),
+        };
+
+        if let Some(axis_name) = name.strip_suffix('+') {
+            find_in(&AXES, axis_name).map(Binding::AxisPositive)
+        } else if let Some(axis_name) = name.strip_suffix('-') {
+            find_in(&AXES, axis_name).map(Binding::AxisNegative)
+        } else {
+            find_in(&BUTTONS, name).map(Binding::Button)
+        }
+    }
+
+    /// 입력 장치 종류
+    pub fn device(&self) -> Device {
+        match self {
+            Binding::Key(_) => Device::Keyboard,
+            Binding::Button(_) | Binding::AxisPositive(_) | Binding::AxisNegative(_) => {
+                Device::Gamepad
+            }
+            Binding::Mouse(_) => Device::Mouse,
+        }
+    }
+}
+
+/// 데드존을 뺀 아날로그 축 값 (0 ~ 1)
+fn axis_value(value: f32) -> f32 {
+    if value <= AXIS_DEADZONE {
+        0.
+    } else {
+        ((value - AXIS_DEADZONE) / (1. - AXIS_DEADZONE)).min(1.)
+    }
+}
+
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+    // 이번 틱과 지난 틱에 눌려있던 동작
+    current: HashSet<Action>,
+    previous: HashSet<Action>,
+    // 이번 틱에 눌린 정도 (0 ~ 1), 아날로그 축이 아니면 0 또는 1
+    values: HashMap<Action, f32>,
+    // 이번 틱과 지난 틱에 눌려있던 키와 게임패드 버튼
+    keys: HashSet<KeyCode>,
+    previous_keys: HashSet<KeyCode>,
+    buttons: HashSet<Button>,
+    previous_buttons: HashSet<Button>,
+    // 입력이 들어온 적 있는 게임패드, 연결이 끊기면 지운다.
+    gamepads: Vec<GamepadId>,
+    // 눌려있는 마우스 버튼
+    mouse_buttons: HashSet<MouseButton>,
+    // 마우스 x 위치 (가상해상도 기준)
+    mouse_x: f32,
+    // paddle이 마우스를 따라가는지 여부와 그 최고 속도 (초당 픽셀)
+    pub mouse_paddle: bool,
+    pub mouse_speed: f32,
+    // 키 입력을 받는 중이면 동작은 모두 눌리지 않은 것으로 본다.
+    // 눌린 동작의 기록은 계속한다.
+    capturing: bool,
+}
+
+impl Default for InputMap {
+    fn default() -> InputMap {
+        InputMap::new()
+    }
+}
+
+impl InputMap {
+    /// 기본 키 설정으로 만든다.
+    pub fn new() -> InputMap {
+        let mut input = InputMap::empty();
+
+        input.bind(Action::MoveLeft, Binding::Key(KeyCode::Left));
+        input.bind(Action::MoveRight, Binding::Key(KeyCode::Right));
+        input.bind(Action::AimLeft, Binding::Key(KeyCode::A));
+        input.bind(Action::AimRight, Binding::Key(KeyCode::D));
+        input.bind(Action::Serve, Binding::Key(KeyCode::Space));
+        input.bind(Action::Serve, Binding::Mouse(MouseButton::Left));
+        input.bind(Action::Pause, Binding::Key(KeyCode::P));
+        input.bind(Action::Confirm, Binding::Key(KeyCode::Return));
+        input.bind(Action::Back, Binding::Key(KeyCode::X));
+        input.bind(Action::MenuUp, Binding::Key(KeyCode::Up));
+        input.bind(Action::MenuDown, Binding::Key(KeyCode::Down));
+        input.bind(Action::Quit, Binding::Key(KeyCode::Escape));
+
+        input.bind(Action::MoveLeft, Binding::Button(Button::DPadLeft));
+        input.bind(Action::MoveLeft, Binding::AxisNegative(Axis::LeftStickX));
+        input.bind(Action::MoveRight, Binding::Button(Button::DPadRight));
+        input.bind(Action::MoveRight, Binding::AxisPositive(Axis::LeftStickX));
+        input.bind(Action::AimLeft, Binding::AxisNegative(Axis::RightStickX));
+        input.bind(Action::AimRight, Binding::AxisPositive(Axis::RightStickX));
+        input.bind(Action::Serve, Binding::Button(Button::West));
+        input.bind(Action::Pause, Binding::Button(Button::Start));
+        input.bind(Action::Confirm, Binding::Button(Button::South));
+        input.bind(Action::Back, Binding::Button(Button::East));
+        input.bind(Action::MenuUp, Binding::Button(Button::DPadUp));
+        input.bind(Action::MenuUp, Binding::AxisPositive(Axis::LeftStickY));
+        input.bind(Action::MenuDown, Binding::Button(Button::DPadDown));
+        input.bind(Action::MenuDown, Binding::AxisNegative(Axis::LeftStickY));
+
+        input
+    }
+
+    /// 연결된 입력이 없는 InputMap
+    fn empty() -> InputMap {
+        InputMap {
+            bindings: HashMap::new(),
+            current: HashSet::new(),
+            previous: HashSet::new(),
+            values: HashMap::new(),
+            keys: HashSet::new(),
+            previous_keys: HashSet::new(),
+            buttons: HashSet::new(),
+            previous_buttons: HashSet::new(),
+            gamepads: vec![],
+            mouse_buttons: HashSet::new(),
+            mouse_x: 0.,
+            mouse_paddle: false,
+            mouse_speed: MOUSE_SPEED,
+            capturing: false,
+        }
+    }
+
+    /// 설정 파일을 읽는다.
+    /// 파일이 없으면 기본 설정을, 잘못된 항목은 경고를 출력하고 기본 설정을 사용한다.
+    pub fn load(ctx: &mut Context) -> InputMap {
+        if !filesystem::exists(ctx, BINDINGS_FILE) {
+            return InputMap::new();
+        }
+
+        let mut contents = String::new();
+        let read = filesystem::open(ctx, BINDINGS_FILE)
+            .map_err(|e| e.to_string())
+            .and_then(|mut file| {
+                file.read_to_string(&mut contents)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = read {
+            println!("{}: {}", BINDINGS_FILE, e);
+            return InputMap::new();
+        }
+
+        let (input, warnings) = InputMap::parse(&contents);
+        for warning in warnings {
+            println!("{}: {}", BINDINGS_FILE, warning);
+        }
+
+        input
+    }
+
+    /// 설정 파일 내용을 해석한다.
+    /// 해석한 설정과 경고 목록을 반환한다.
+    pub fn parse(contents: &str) -> (InputMap, Vec<String>) {
+        let defaults = InputMap::new();
+        let mut input = InputMap::empty();
+        let mut warnings = Vec::<String>::new();
+        let mut configured = HashSet::<Action>::new();
+
+        for (idx, raw) in contents.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = match line.find('=') {
+                Some(eq) => (line[..eq].trim(), line[eq + 1..].trim()),
+                None => {
+                    warnings.push(format!("line {}: expected `action = key, ...`", line_no));
+                    continue;
+                }
+            };
+
+            // 마우스 paddle 설정
+            match name {
+                "mouse_paddle" => {
+                    match value {
+                        "on" | "true" => input.mouse_paddle = true,
+                        "off" | "false" => input.mouse_paddle = false,
+                        _ => warnings.push(format!(
+                            "line {}: expected `on` or `off`, found `{}`",
+                            line_no, value
+                        )),
+                    }
+                    continue;
+                }
+                "mouse_speed" => {
+                    match value.parse::<f32>() {
+                        Ok(speed) if speed > 0. => input.mouse_speed = speed,
+                        _ => warnings
+                            .push(format!("line {}: invalid mouse speed `{}`", line_no, value)),
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => {
+                    warnings.push(format!("line {}: unknown action `{}`", line_no, name));
+                    continue;
+                }
+            };
+
+            configured.insert(action);
+
+            for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let binding = match Binding::parse(name) {
+                    Some(binding) => binding,
+                    None => {
+                        warnings.push(format!("line {}: unknown key `{}`", line_no, name));
+                        continue;
+                    }
+                };
+
+                match input.conflict(action, binding) {
+                    Some(other) => warnings.push(format!(
+                        "line {}: `{}` is already bound to `{}`",
+                        line_no,
+                        name,
+                        other.name()
+                    )),
+                    None => input.bind(action, binding),
+                }
+            }
+        }
+
+        // 파일에 없거나 연결된 키가 없는 동작은 겹치지 않는 기본 키를 사용한다.
+        for action in Action::ALL.iter() {
+            if !input.bindings(*action).is_empty() {
+                continue;
+            }
+
+            if configured.contains(action) {
+                warnings.push(format!(
+                    "`{}` has no usable keys, using defaults",
+                    action.name()
+                ));
+            }
+            for binding in defaults.bindings(*action) {
+                match input.conflict(*action, *binding) {
+                    Some(other) => warnings.push(format!(
+                        "default key `{}` of `{}` is already bound to `{}`",
+                        binding.name().unwrap_or_default(),
+                        action.name(),
+                        other.name()
+                    )),
+                    None => input.bind(*action, *binding),
+                }
+            }
+        }
+
+        (input, warnings)
+    }
+
+    /// 설정을 파일에 쓴다.
+    pub fn write(&self, ctx: &mut Context) -> GameResult {
+        let mut file = filesystem::create(ctx, BINDINGS_FILE)?;
+        file.write_all(self.contents().as_bytes())?;
+
+        Ok(())
+    }
+
+    /// 설정 파일 내용, parse로 다시 읽을 수 있다.
+    pub fn contents(&self) -> String {
+        let mut contents = String::new();
+        for action in Action::ALL.iter() {
+            let names: Vec<String> = self
+                .bindings(*action)
+                .iter()
+                .filter_map(|binding| binding.name())
+                .collect();
+            writeln!(contents, "{} = {}", action.name(), names.join(", ")).unwrap();
+        }
+
+        writeln!(
+            contents,
+            "mouse_paddle = {}",
+            if self.mouse_paddle { "on" } else { "off" }
+        )
+        .unwrap();
+        writeln!(contents, "mouse_speed = {}", self.mouse_speed).unwrap();
+
+        contents
+    }
+
+    /// 입력이 같은 곳에서 쓰이는 다른 동작에 이미 연결되어 있다면 그 동작을 반환한다.
+    pub fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .find(|other| {
+                **other != action
+                    && action.overlaps(**other)
+                    && self.bindings(**other).contains(&binding)
+            })
+            .copied()
+    }
+
+    /// 동작에 입력을 추가로 연결한다.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// 모든 동작과 마우스 설정을 기본 설정으로 되돌린다.
+    pub fn reset_bindings(&mut self) {
+        let defaults = InputMap::new();
+        self.bindings = defaults.bindings;
+        self.mouse_paddle = defaults.mouse_paddle;
+        self.mouse_speed = defaults.mouse_speed;
+    }
+
+    /// 동작에 연결된 같은 종류(키보드, 게임패드)의 입력을 새 입력으로 바꾼다.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|b| b.device() != binding.device());
+        }
+        self.bind(action, binding);
+    }
+
+    /// 동작에 연결된 입력 목록
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |b| b.as_slice())
+    }
+
+    /// 현재 입력 장치 상태를 읽는다. 매 틱마다 한번 호출한다.
+    pub fn update(&mut self, ctx: &mut Context) {
+        // 연결이 끊긴 게임패드는 잊는다. 다시 연결되면 add_gamepad로 돌아온다.
+        self.gamepads
+            .retain(|id| gamepad::gamepad(ctx, *id).is_connected());
+
+        let mut buttons = HashSet::<Button>::new();
+        for id in self.gamepads.iter() {
+            let pad = gamepad::gamepad(ctx, *id);
+            buttons.extend(
+                BUTTONS
+                    .iter()
+                    .map(|(button, _)| *button)
+                    .filter(|button| pad.is_pressed(*button)),
+            );
+        }
+
+        self.previous_keys = std::mem::replace(&mut self.keys, keyboard::pressed_keys(ctx).clone());
+
+        // 마우스 위치는 창 좌표이므로 가상해상도로 바꾼다.
+        let (width, _) = graphics::drawable_size(ctx);
+        if width > 0. {
+            self.mouse_x = mouse::position(ctx).x / width * game::VIRTUAL_WIDTH;
+        }
+        self.mouse_buttons = MOUSE_BUTTONS
+            .iter()
+            .map(|(button, _)| *button)
+            .filter(|button| mouse::button_pressed(ctx, *button))
+            .collect();
+        self.previous_buttons = std::mem::replace(&mut self.buttons, buttons);
+
+        let axis = |axis: Axis, sign: f32| {
+            self.gamepads
+                .iter()
+                .map(|id| axis_value(gamepad::gamepad(ctx, *id).value(axis) * sign))
+                .fold(0., f32::max)
+        };
+        let values = self.action_values(axis);
+        self.set_values(values);
+    }
+
+    /// 동작마다 연결된 입력 중 가장 많이 눌린 정도 (0 ~ 1)
+    /// axis는 아날로그 축을 주어진 방향으로 기울인 정도를 알려준다.
+    fn action_values(&self, axis: impl Fn(Axis, f32) -> f32) -> Vec<(Action, f32)> {
+        let down = |pressed: bool| if pressed { 1. } else { 0. };
+
+        self.bindings
+            .iter()
+            .map(|(action, bindings)| {
+                let value = bindings
+                    .iter()
+                    .map(|binding| match binding {
+                        Binding::Key(key) => down(self.keys.contains(key)),
+                        Binding::Button(button) => down(self.buttons.contains(button)),
+                        Binding::Mouse(button) => down(self.mouse_buttons.contains(button)),
+                        Binding::AxisPositive(a) => axis(*a, 1.),
+                        Binding::AxisNegative(a) => axis(*a, -1.),
+                    })
+                    .fold(0., f32::max);
+                (*action, value)
+            })
+            .collect()
+    }
+
+    /// 이번 틱의 동작 상태를 기록한다.
+    /// 키 입력을 받는 중에도 기록은 계속해서, 입력 받기를 끝낸 키가
+    /// 다음 틱에 새로 눌린 것으로 보이지 않게 한다.
+    fn set_values(&mut self, values: Vec<(Action, f32)>) {
+        self.previous = std::mem::take(&mut self.current);
+        self.values.clear();
+
+        for (action, value) in values {
+            if value > 0. {
+                self.values.insert(action, value);
+            }
+            if value >= AXIS_PRESS {
+                self.current.insert(action);
+            }
+        }
+    }
+
+    /// 입력이 들어온 게임패드를 등록한다.
+    /// 게임 중에 새로 연결된 게임패드도 첫 입력부터 사용된다.
+    pub fn add_gamepad(&mut self, id: GamepadId) {
+        if !self.gamepads.contains(&id) {
+            self.gamepads.push(id);
+        }
+    }
+
+    /// 다음에 눌리는 키를 받기 시작한다.
+    pub fn capture(&mut self) {
+        self.capturing = true;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// 키 입력을 받는 중에 이번 틱에 새로 눌린 키나 게임패드 버튼
+    /// 입력을 받으면 입력 받기를 끝낸다.
+    pub fn captured(&mut self) -> Option<Binding> {
+        if !self.capturing {
+            return None;
+        }
+
+        let key = self
+            .keys
+            .iter()
+            .find(|key| !self.previous_keys.contains(key))
+            .map(|key| Binding::Key(*key));
+        let button = self
+            .buttons
+            .iter()
+            .find(|button| !self.previous_buttons.contains(button))
+            .map(|button| Binding::Button(*button));
+
+        let binding = key.or(button);
+        if binding.is_some() {
+            self.capturing = false;
+        }
+
+        binding
+    }
+
+    /// 마우스 paddle 설정이 켜져 있다면 paddle이 따라갈 x 위치 (가상해상도 기준)
+    pub fn mouse_target(&self) -> Option<f32> {
+        if self.mouse_paddle {
+            Some(self.mouse_x)
+        } else {
+            None
+        }
+    }
+
+    /// 동작이 눌린 정도 (0 ~ 1)
+    /// 아날로그 축에 연결된 동작은 기울인 만큼의 값이 된다.
+    pub fn value(&self, action: Action) -> f32 {
+        if self.capturing {
+            return 0.;
+        }
+        self.values.get(&action).copied().unwrap_or(0.)
+    }
+
+    /// 동작이 눌려있는지 확인
+    pub fn pressed(&self, action: Action) -> bool {
+        !self.capturing && self.current.contains(&action)
+    }
+
+    /// 이번 틱에 처음 눌렸는지 확인
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action) && !self.previous.contains(&action)
+    }
+
+    /// 이번 틱에 떼어졌는지 확인
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.capturing && !self.current.contains(&action) && self.previous.contains(&action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_named_input_round_trips() {
+        let bindings = KEYS
+            .iter()
+            .map(|(key, _)| Binding::Key(*key))
+            .chain(BUTTONS.iter().map(|(button, _)| Binding::Button(*button)))
+            .chain(AXES.iter().map(|(axis, _)| Binding::AxisPositive(*axis)))
+            .chain(AXES.iter().map(|(axis, _)| Binding::AxisNegative(*axis)))
+            .chain(
+                MOUSE_BUTTONS
+                    .iter()
+                    .map(|(button, _)| Binding::Mouse(*button)),
+            );
+
+        for binding in bindings {
+            let name = binding.name().unwrap();
+            assert_eq!(Binding::parse(&name), Some(binding), "{}", name);
+        }
+    }
+
+    #[test]
+    fn unlisted_keys_have_no_name() {
+        assert_eq!(Binding::Key(KeyCode::Compose).name(), None);
+        assert_eq!(Binding::parse("Compose"), None);
+    }
+
+    #[test]
+    fn actions_read_on_one_screen_overlap() {
+        // PlayState는 Back으로 타이틀로 돌아가고, PauseState는 Confirm으로 돌아간다.
+        assert!(Action::Back.overlaps(Action::Serve));
+        assert!(Action::Confirm.overlaps(Action::Pause));
+        assert!(Action::Quit.overlaps(Action::AimLeft));
+        assert!(!Action::Serve.overlaps(Action::Confirm));
+        assert!(!Action::AimLeft.overlaps(Action::MenuUp));
+    }
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        let input = InputMap::new();
+        for action in Action::ALL.iter() {
+            for binding in input.bindings(*action) {
+                assert_eq!(input.conflict(*action, *binding), None, "{:?}", binding);
+            }
+        }
+    }
+
+    /// 키보드에서 keys만 눌린 한 틱을 흉내낸다.
+    fn tick(input: &mut InputMap, keys: &[KeyCode]) {
+        input.previous_keys = std::mem::replace(&mut input.keys, keys.iter().copied().collect());
+        let values = input.action_values(|_, _| 0.);
+        input.set_values(values);
+    }
+
+    #[test]
+    fn key_that_ends_a_capture_is_not_pressed_afterwards() {
+        let mut input = InputMap::new();
+        tick(&mut input, &[]);
+        input.capture();
+
+        // Back(X)으로 입력 받기를 끝낸 틱에도, 그 다음 틱에도 Back은 눌리지 않는다.
+        tick(&mut input, &[KeyCode::X]);
+        assert!(!input.just_pressed(Action::Back));
+        assert_eq!(input.captured(), Some(Binding::Key(KeyCode::X)));
+        tick(&mut input, &[KeyCode::X]);
+        assert!(!input.just_pressed(Action::Back));
+        assert!(input.pressed(Action::Back));
+
+        // 떼었다 다시 누르면 평소처럼 눌린다.
+        tick(&mut input, &[]);
+        tick(&mut input, &[KeyCode::X]);
+        assert!(input.just_pressed(Action::Back));
+    }
+
+    #[test]
+    fn actions_are_not_pressed_while_capturing() {
+        let mut input = InputMap::new();
+        tick(&mut input, &[]);
+        input.capture();
+
+        tick(&mut input, &[KeyCode::Escape]);
+        assert!(!input.just_pressed(Action::Quit));
+        assert!(!input.pressed(Action::Quit));
+        assert_eq!(input.value(Action::Quit), 0.);
+
+        input.captured();
+        tick(&mut input, &[KeyCode::Escape]);
+        assert!(!input.just_pressed(Action::Quit));
+    }
+
+    #[test]
+    fn parse_warns_about_unknown_actions() {
+        let (input, warnings) = InputMap::parse("jump = Space\n");
+
+        assert_eq!(warnings, vec!["line 1: unknown action `jump`".to_owned()]);
+        let defaults = InputMap::new();
+        for action in Action::ALL.iter() {
+            assert_eq!(input.bindings(*action), defaults.bindings(*action));
+        }
+    }
+
+    #[test]
+    fn parse_skips_unknown_keys() {
+        let (input, warnings) = InputMap::parse("serve = Banana, Space\n");
+
+        assert_eq!(warnings, vec!["line 1: unknown key `Banana`".to_owned()]);
+        assert_eq!(
+            input.bindings(Action::Serve),
+            &[Binding::Key(KeyCode::Space)]
+        );
+    }
+
+    #[test]
+    fn conflicting_entry_falls_back_to_defaults() {
+        let (input, warnings) = InputMap::parse("move_left = A\naim_left = A\n");
+
+        assert_eq!(
+            warnings,
+            vec![
+                "line 2: `A` is already bound to `move_left`".to_owned(),
+                "`aim_left` has no usable keys, using defaults".to_owned(),
+                "default key `A` of `aim_left` is already bound to `move_left`".to_owned(),
+            ]
+        );
+        assert_eq!(
+            input.bindings(Action::MoveLeft),
+            &[Binding::Key(KeyCode::A)]
+        );
+        // 겹치지 않는 기본 입력만 남는다.
+        assert_eq!(
+            input.bindings(Action::AimLeft),
+            &[Binding::AxisNegative(Axis::RightStickX)]
+        );
+    }
+
+    #[test]
+    fn written_contents_parse_back() {
+        let mut input = InputMap::new();
+        input.rebind(Action::Serve, Binding::Key(KeyCode::W));
+        input.bind(Action::Pause, Binding::Button(Button::Select));
+        input.mouse_paddle = true;
+        input.mouse_speed = 450.;
+
+        let (parsed, warnings) = InputMap::parse(&input.contents());
+
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        for action in Action::ALL.iter() {
+            assert_eq!(parsed.bindings(*action), input.bindings(*action));
+        }
+        assert!(parsed.mouse_paddle);
+        assert_eq!(parsed.mouse_speed, 450.);
+    }
+}
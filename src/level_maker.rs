@@ -1,23 +1,418 @@
-use crate::objects::Block;
-use rand::*;
+//! 레벨 생성기
+//! `resources/levels/level<번호>.txt` 파일이 있으면 그 파일을 읽어 레벨을 만들고,
+//! 없으면 난수로 블럭 배치를 만든다.
+//!
+//! 레벨 파일 형식
+//!
+//! ```text
+//! # 주석
+//! name: First Steps
+//! music: /music.wav
+//! background: /background.png
+//! ---
+//! .  10 10 10 .
+//! 21 21 21 21 21
+//! ```
+//!
+//! `---` 위는 메타데이터(`키: 값`), 아래는 블럭 격자이다.
+//! 격자의 각 칸은 공백으로 구분하며 `.` 은 빈 칸,
+//! 그 외에는 `<색상 1-5><단계 0-3>[종류]` 이다. 종류는 생략하면 `n`(일반 블럭)이다.
+//! `L` 은 잠긴 블럭이며 한 레벨에 하나만 둘 수 있다.
+//! 같은 메타데이터 키는 한 번만 쓸 수 있다.
 
-pub fn create_map(_level: i32) -> Vec<Block> {
+use crate::game;
+use crate::objects::{Block, BlockKind, BLOCK_COLORS, BLOCK_TIERS};
+use ggez::filesystem;
+use ggez::Context;
+use rand::Rng;
+use std::fmt;
+use std::io::Read;
+
+// 한 줄에 놓을 수 있는 최대 블럭 수와 최대 줄 수
+pub const MAX_COLUMNS: usize = 13;
+pub const MAX_ROWS: usize = 8;
+
+// 잠긴 블럭이 나오기 시작하는 레벨과 나올 확률
+pub const LOCKED_BLOCK_LEVEL: i32 = 3;
+pub const LOCKED_BLOCK_CHANCE: f64 = 0.5;
+
+/// 레벨 파일을 읽어 만든 레벨 정보
+pub struct Level {
+    pub name: String,
+    pub music: Option<String>,
+    pub background: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+/// 레벨 파일 해석 오류
+/// 줄과 칸 번호는 1부터 시작하며, 파일 자체를 읽지 못한 경우에는 0이다.
+#[derive(Debug)]
+pub struct LevelError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+/// 레벨 번호에 해당하는 파일 경로
+pub fn level_path(level: i32) -> String {
+    format!("/levels/level{}.txt", level)
+}
+
+/// 레벨 파일을 읽는다.
+/// 해당 레벨의 파일이 없으면 None을 반환한다.
+pub fn load_level(ctx: &mut Context, level: i32) -> Option<Result<Level, LevelError>> {
+    let path = level_path(level);
+
+    if !filesystem::exists(ctx, &path) {
+        return None;
+    }
+
+    let mut source = String::new();
+    let read = filesystem::open(ctx, &path)
+        .map_err(|e| e.to_string())
+        .and_then(|mut file| file.read_to_string(&mut source).map_err(|e| e.to_string()));
+
+    Some(match read {
+        Ok(_) => parse_level(&path, &source),
+        Err(message) => Err(LevelError {
+            file: path,
+            line: 0,
+            column: 0,
+            message,
+        }),
+    })
+}
+
+/// 레벨 파일 내용을 해석한다.
+///
+/// # Arguments
+///
+/// * `file` - 오류 메시지에 표시할 파일 이름
+/// * `source` - 레벨 파일 내용
+///
+pub fn parse_level(file: &str, source: &str) -> Result<Level, LevelError> {
+    let error = |line: usize, column: usize, message: String| LevelError {
+        file: file.to_owned(),
+        line,
+        column,
+        message,
+    };
+
+    let mut level = Level {
+        name: String::new(),
+        music: None,
+        background: None,
+        blocks: vec![],
+    };
+
+    let mut in_grid = false;
+    let mut locked = false;
+    let mut keys = Vec::<String>::new();
+    let mut rows = Vec::<Vec<Option<Block>>>::new();
+
+    for (idx, raw) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw.trim_end();
+
+        if line.trim_start().starts_with('#') || (!in_grid && line.trim().is_empty()) {
+            continue;
+        }
+
+        if !in_grid {
+            if line.trim() == "---" {
+                in_grid = true;
+                continue;
+            }
+
+            let colon = match line.find(':') {
+                Some(colon) => colon,
+                None => {
+                    return Err(error(
+                        line_no,
+                        1,
+                        "expected `key: value` or `---`".to_owned(),
+                    ))
+                }
+            };
+            let key = line[..colon].trim();
+            let value = line[colon + 1..].trim().to_owned();
+            let key_column = line.find(key).unwrap_or(0) + 1;
+
+            // 같은 키가 두 번 나오면 어느 값을 쓸지 알 수 없다.
+            if keys.iter().any(|k| k == key) {
+                return Err(error(
+                    line_no,
+                    key_column,
+                    format!("duplicate key `{}`", key),
+                ));
+            }
+            keys.push(key.to_owned());
+
+            match key {
+                "name" => level.name = value,
+                "music" => level.music = Some(value),
+                "background" => level.background = Some(value),
+                _ => return Err(error(line_no, key_column, format!("unknown key `{}`", key))),
+            }
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if rows.len() >= MAX_ROWS {
+            return Err(error(
+                line_no,
+                1,
+                format!("too many rows (at most {})", MAX_ROWS),
+            ));
+        }
+
+        let mut row = Vec::<Option<Block>>::new();
+        for (column, cell) in tokens(line) {
+            if row.len() >= MAX_COLUMNS {
+                return Err(error(
+                    line_no,
+                    column,
+                    format!("too many columns (at most {})", MAX_COLUMNS),
+                ));
+            }
+
+            let block = parse_cell(cell).map_err(|message| error(line_no, column, message))?;
+            if let Some(BlockKind::LOCKED) = block.as_ref().map(|b| b.kind) {
+                if locked {
+                    return Err(error(
+                        line_no,
+                        column,
+                        "only one locked block `L` is allowed per level".to_owned(),
+                    ));
+                }
+                locked = true;
+            }
+            row.push(block);
+        }
+        rows.push(row);
+    }
+
+    if !in_grid {
+        return Err(error(
+            source.lines().count(),
+            1,
+            "missing `---` before block grid".to_owned(),
+        ));
+    }
+
+    // 가장 긴 줄을 기준으로 가운데 정렬한다.
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let offset = (game::VIRTUAL_WIDTH - width as f32 * 32.) / 2.;
+
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            if let Some(mut block) = cell {
+                block.x = offset + x as f32 * 32.;
+                block.y = (y + 1) as f32 * 16.;
+                level.blocks.push(block);
+            }
+        }
+    }
+
+    Ok(level)
+}
+
+/// 공백으로 구분된 (1부터 시작하는 칸 번호, 토큰) 목록
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut result = Vec::<(usize, &str)>::new();
+    let mut start: Option<usize> = None;
+
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                result.push((line[..s].chars().count() + 1, &line[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+
+    if let Some(s) = start {
+        result.push((line[..s].chars().count() + 1, &line[s..]));
+    }
+
+    result
+}
+
+/// 격자의 한 칸을 해석한다. 빈 칸이면 None
+fn parse_cell(cell: &str) -> Result<Option<Block>, String> {
+    if cell == "." {
+        return Ok(None);
+    }
+
+    if cell == "L" {
+        let mut block = Block::new(0., 0.);
+        block.lock();
+        return Ok(Some(block));
+    }
+
+    let chars: Vec<char> = cell.chars().collect();
+    if chars.len() < 2 || chars.len() > 3 {
+        return Err(format!(
+            "invalid cell `{}`, expected `.`, `L` or `<color><tier>[kind]`",
+            cell
+        ));
+    }
+
+    let color = match chars[0].to_digit(10) {
+        Some(c) if c >= 1 && c as i32 <= BLOCK_COLORS => c as i32,
+        _ => {
+            return Err(format!(
+                "invalid color `{}` in `{}` (1-{})",
+                chars[0], cell, BLOCK_COLORS
+            ))
+        }
+    };
+
+    let tier = match chars[1].to_digit(10) {
+        Some(t) if (t as i32) < BLOCK_TIERS => t as i32,
+        _ => {
+            return Err(format!(
+                "invalid tier `{}` in `{}` (0-{})",
+                chars[1],
+                cell,
+                BLOCK_TIERS - 1
+            ))
+        }
+    };
+
+    let kind = match chars.get(2) {
+        None | Some('n') => BlockKind::NORMAL,
+        Some(k) => return Err(format!("unknown block kind `{}` in `{}`", k, cell)),
+    };
+
+    let mut block = Block::new(0., 0.);
+    block.color = color;
+    block.tier = tier;
+    block.kind = kind;
+
+    Ok(Some(block))
+}
+
+/// 주어진 rng로 블럭 배치를 만든다.
+/// 같은 시드의 rng라면 항상 같은 배치가 나온다.
+/// 레벨이 올라갈수록 줄과 칸이 늘어나고, 더 높은 색상과 단계의 블럭이 나온다.
+pub fn create_map<R: Rng>(level: i32, rng: &mut R) -> Vec<Block> {
     let mut blocks = Vec::<Block>::new();
 
-    let mut rng = thread_rng();
-    let rows = rng.gen_range(2, 6);
-    let cols = rng.gen_range(7, 13);
+    let level = level.max(1);
+    let rows = (rng.gen_range(1, 5) + (level - 1) / 2).min(MAX_ROWS as i32);
+    let cols = (rng.gen_range(6, 12) + (level - 1) / 3).min(MAX_COLUMNS as i32);
+
+    // 레벨 1에서는 파란색 0단계 블럭만 나온다.
+    let highest_color = level.min(BLOCK_COLORS);
+    let highest_tier = ((level - 1) / 2).min(BLOCK_TIERS - 1);
 
-    for y in 1..rows {
-        for x in 1..cols {
-            let block = Block::new(
-                ((x - 1) * 32 + 8 + (13 - cols) * 16) as f32,
-                (y * 16) as f32,
-            );
+    let offset = (game::VIRTUAL_WIDTH - cols as f32 * 32.) / 2.;
+
+    for y in 0..rows {
+        // 한 줄은 같은 색상, 단계로 채운다.
+        let color = rng.gen_range(1, highest_color + 1);
+        let tier = rng.gen_range(0, highest_tier + 1);
+
+        for x in 0..cols {
+            let mut block = Block::new(offset + (x * 32) as f32, ((y + 1) * 16) as f32);
+            block.color = color;
+            block.tier = tier;
 
             blocks.push(block);
         }
     }
 
+    // 일정 레벨부터는 잠긴 블럭이 최대 하나 나온다.
+    if level >= LOCKED_BLOCK_LEVEL && rng.gen_bool(LOCKED_BLOCK_CHANCE) {
+        let idx = rng.gen_range(0, blocks.len());
+        blocks[idx].lock();
+    }
+
     blocks
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_metadata_and_grid() {
+        let source = "# 주석\nname: First Steps\nmusic: /music.wav\n---\n.  10 10 .\n21 21 L 32\n";
+        let level = parse_level("level1.txt", source).unwrap();
+
+        assert_eq!(level.name, "First Steps");
+        assert_eq!(level.music.as_deref(), Some("/music.wav"));
+        assert_eq!(level.background, None);
+        assert_eq!(level.blocks.len(), 6);
+        assert_eq!(
+            level
+                .blocks
+                .iter()
+                .filter(|b| b.kind == BlockKind::LOCKED)
+                .count(),
+            1
+        );
+
+        let last = level.blocks.last().unwrap();
+        assert_eq!((last.color, last.tier), (3, 2));
+    }
+
+    #[test]
+    fn errors_report_line_and_column() {
+        let source = "name: Bad Color\n---\n10 10\n10 .  60\n";
+        let err = parse_level("level2.txt", source).err().unwrap();
+
+        assert_eq!((err.line, err.column), (4, 7));
+        assert!(err.message.contains("invalid color"));
+        assert!(err.to_string().starts_with("level2.txt:4:7: "));
+    }
+
+    #[test]
+    fn rejects_unknown_cells() {
+        for cell in ["10x", "1", "1234", "??", "l"].iter() {
+            let source = format!("---\n10 {}\n", cell);
+            let err = parse_level("level.txt", &source).err().unwrap();
+
+            assert_eq!((err.line, err.column), (2, 4), "cell `{}`", cell);
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        let source = "name: One\nmusic: /a.wav\n  name: Two\n---\n10\n";
+        let err = parse_level("level.txt", source).err().unwrap();
+
+        assert_eq!((err.line, err.column), (3, 3));
+        assert!(err.message.contains("duplicate key `name`"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_missing_grid() {
+        let err = parse_level("level.txt", "name: x\nsong: y\n---\n")
+            .err()
+            .unwrap();
+        assert_eq!((err.line, err.column), (2, 1));
+
+        let err = parse_level("level.txt", "name: x\n").err().unwrap();
+        assert!(err.message.contains("missing `---`"));
+    }
+}
@@ -1,7 +1,11 @@
 pub mod game;
+pub mod grid;
+pub mod input;
 pub mod level_maker;
 pub mod objects;
+pub mod particles;
 pub mod quad;
 pub mod reg;
 pub mod save;
+pub mod simulation;
 pub mod states;
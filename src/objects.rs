@@ -1,14 +1,14 @@
 use crate::game;
-use crate::states::{play_sound, play_sound_once};
 
 use crate::reg::Reg;
 use ggez::Context;
-use rand::*;
+use rand::Rng;
 
 pub const PADDLE_FLAG: i32 = 0b0000_0000_0000_0000_0001_0000_0000_0000;
 pub const BALL_FLAG: i32 = 0b0000_0000_0000_0000_0010_0000_0000_0000;
 pub const BLOCK_FLAG: i32 = 0b0000_0000_0000_0000_0100_0000_0000_0000;
 pub const HEARTS_FLAG: i32 = 0b0000_0000_0000_0000_1000_0000_0000_0000;
+pub const POWERUP_FLAG: i32 = 0b0000_0000_0000_0001_0000_0000_0000_0000;
 pub const BLUE: i32 = 1;
 pub const GREEN: i32 = 2;
 pub const RED: i32 = 4;
@@ -27,58 +27,137 @@ pub const SIZE_MASK: i32 = 0b1111_0000;
 
 pub const PADDLE_SPEED: f32 = 200.;
 
-#[derive(PartialEq, Debug)]
-pub enum CollideFlag {
-    TOP,
-    BOTTOM,
-    LEFT,
-    RIGHT,
-    NONE,
+// paddle에서 튕겨나가는 공의 최대 각도 (수직 기준, 라디안)
+// 공이 수평으로 날아가지 않도록 수직 속도는 항상 cos(최대 각도) 이상이 된다.
+pub const MAX_BOUNCE_ANGLE: f32 = std::f32::consts::PI / 3.;
+// paddle이 최고 속도로 움직일 때 더해지는 각도
+pub const PADDLE_SPIN_ANGLE: f32 = std::f32::consts::PI / 12.;
+
+// 서브한 공의 빠르기 (초당 픽셀)
+pub const SERVE_SPEED: f32 = 250.;
+
+// 파워업이 떨어지는 속도 (초당 픽셀)
+pub const POWERUP_SPEED: f32 = 60.;
+
+// 블럭 색상은 1 ~ 5, 단계(tier)는 0 ~ 3
+pub const BLOCK_COLORS: i32 = 5;
+pub const BLOCK_TIERS: i32 = 4;
+
+// 잠긴 블럭의 sprite 번호와 깼을 때 점수
+pub const LOCKED_BLOCK_SPRITE: i32 = 21;
+pub const LOCKED_BLOCK_POINTS: i32 = 2000;
+
+/// 충돌 축
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// 겹쳐 있는 두 물체의 충돌 정보
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Contact {
+    // 가장 적게 겹친 축
+    pub axis: Axis,
+    // 그 축으로 겹친 깊이 (픽셀)
+    pub depth: f32,
+    // a를 b 밖으로 밀어낼 방향
+    pub normal: (f32, f32),
 }
 
 pub trait Object {
-    fn update(&mut self, ctx: &mut Context, reg: &mut Reg, dt: f32);
-    fn draw(&mut self, ctx: &mut Context, reg: &mut Reg);
+    fn draw(&self, ctx: &mut Context, reg: &mut Reg);
     fn set_sprite(&mut self, idx: i32);
     fn get_xywh(&self) -> (f32, f32, f32, f32);
 }
 
 /// AABB Collide
-pub fn collide_aabb(a: &dyn Object, b: &dyn Object) -> Vec<CollideFlag> {
-    let a_xywh = a.get_xywh();
-    let b_xywh = b.get_xywh();
-
-    if (a_xywh.0 < b_xywh.0 + b_xywh.2)
-        && (a_xywh.0 + a_xywh.2 > b_xywh.0)
-        && (a_xywh.1 < b_xywh.1 + b_xywh.3)
-        && (a_xywh.1 + a_xywh.3 > b_xywh.1)
-    {
-        // 충돌시에 어느 면에 부딪히는지를 알려준다.
-        // a.x < b.x -> LEFT
-        // a.x > b.x -> RIGHT
-        // a.y < b.y -> BOTTOM
-        // a.y > b.y -> TOP
-        let mut vec = Vec::<CollideFlag>::new();
-        if a_xywh.0 < b_xywh.0 {
-            vec.push(CollideFlag::LEFT);
-        }
+/// 두 물체의 중심과 크기로 겹친 정도를 구하고, 가장 적게 겹친 축을 충돌 축으로 본다.
+/// 겹치지 않으면 None
+pub fn collide_aabb(a: &dyn Object, b: &dyn Object) -> Option<Contact> {
+    let (ax, ay, aw, ah) = a.get_xywh();
+    let (bx, by, bw, bh) = b.get_xywh();
+
+    // 중심 사이의 거리
+    let cx = (ax + aw / 2.) - (bx + bw / 2.);
+    let cy = (ay + ah / 2.) - (by + bh / 2.);
+
+    // 각 축으로 겹친 깊이
+    let overlap_x = (aw + bw) / 2. - cx.abs();
+    let overlap_y = (ah + bh) / 2. - cy.abs();
+
+    if overlap_x <= 0. || overlap_y <= 0. {
+        return None;
+    }
 
-        if a_xywh.0 > b_xywh.0 {
-            vec.push(CollideFlag::RIGHT);
-        }
+    // 중심이 같으면 위/왼쪽으로 밀어낸다.
+    let sign = |d: f32| if d > 0. { 1. } else { -1. };
 
-        if a_xywh.1 < b_xywh.1 {
-            vec.push(CollideFlag::BOTTOM);
+    Some(if overlap_x < overlap_y {
+        Contact {
+            axis: Axis::X,
+            depth: overlap_x,
+            normal: (sign(cx), 0.),
+        }
+    } else {
+        Contact {
+            axis: Axis::Y,
+            depth: overlap_y,
+            normal: (0., sign(cy)),
         }
+    })
+}
 
-        if a_xywh.1 > b_xywh.1 {
-            vec.push(CollideFlag::TOP);
+/// 이동하는 물체가 다른 물체에 처음 닿는 순간의 정보
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Sweep {
+    // 이동량 중 닿기까지의 비율 (0 ~ 1)
+    pub time: f32,
+    // 닿은 면의 법선, 이동하던 물체가 튕겨나갈 방향
+    pub normal: (f32, f32),
+}
+
+/// Swept AABB Collide
+/// a가 (dx, dy) 만큼 이동하는 동안 b에 처음 닿는 시각과 면을 구한다.
+/// 이미 겹쳐 있거나 닿지 않으면 None
+pub fn sweep_aabb(a: &dyn Object, dx: f32, dy: f32, b: &dyn Object) -> Option<Sweep> {
+    let (ax, ay, aw, ah) = a.get_xywh();
+    let (bx, by, bw, bh) = b.get_xywh();
+
+    // 각 축에서 닿기 시작하는 시각과 떨어지는 시각
+    let axis = |a_pos: f32, a_len: f32, b_pos: f32, b_len: f32, d: f32| {
+        if d > 0. {
+            Some(((b_pos - (a_pos + a_len)) / d, (b_pos + b_len - a_pos) / d))
+        } else if d < 0. {
+            Some(((b_pos + b_len - a_pos) / d, (b_pos - (a_pos + a_len)) / d))
+        } else if a_pos + a_len <= b_pos || a_pos >= b_pos + b_len {
+            // 이 축으로 움직이지 않는데 겹치지도 않으면 닿을 수 없다.
+            None
+        } else {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
         }
+    };
 
-        vec
-    } else {
-        vec![]
+    let (x_entry, x_exit) = axis(ax, aw, bx, bw, dx)?;
+    let (y_entry, y_exit) = axis(ay, ah, by, bh, dy)?;
+
+    let entry = x_entry.max(y_entry);
+    let exit = x_exit.min(y_exit);
+
+    if entry > exit || !(0. ..=1.).contains(&entry) {
+        return None;
     }
+
+    let normal = if x_entry > y_entry {
+        (-dx.signum(), 0.)
+    } else {
+        (0., -dy.signum())
+    };
+
+    Some(Sweep {
+        time: entry,
+        normal,
+    })
 }
 
 // 기본적인 스프라이트 데이터를 모두 초기화한다.
@@ -131,6 +210,12 @@ pub fn init_global_sprite(reg: &mut Reg) {
         }
     }
 
+    // power up
+    // breakout.png 하단의 16x16 아이콘 10개
+    for i in 0..10 {
+        reg.register_sprite(POWERUP_FLAG + i, (i * 16) as f32, 192., 16., 16.);
+    }
+
     // hearts
     reg.register_heart(HEARTS_FLAG, 0., 0., 10., 9.);
     reg.register_heart(HEARTS_FLAG + 1, 10., 0., 10., 9.);
@@ -147,6 +232,7 @@ pub struct Paddle {
 }
 
 impl Paddle {
+    /// 현재 크기에 해당하는 폭
     fn get_width(&self) -> f32 {
         match self.size {
             SMALL => 32.,
@@ -169,27 +255,52 @@ impl Paddle {
             dx: 0.,
         }
     }
-}
 
-impl Object for Paddle {
-    fn update(&mut self, ctx: &mut Context, _reg: &mut Reg, dt: f32) {
-        if ggez::input::keyboard::is_key_pressed(ctx, ggez::input::keyboard::KeyCode::Left) {
-            self.dx = -1. * PADDLE_SPEED;
-        } else if ggez::input::keyboard::is_key_pressed(ctx, ggez::input::keyboard::KeyCode::Right)
-        {
-            self.dx = PADDLE_SPEED;
-        } else {
-            self.dx = 0.;
-        }
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    /// 크기를 바꾼다. 가운데 위치는 그대로 둔다.
+    pub fn set_size(&mut self, size: i32) {
+        let center = self.x + self.width / 2.;
+        self.size = size;
+        self.width = self.get_width();
+        self.x = center - self.width / 2.;
+    }
+
+    /// 입력 방향(-1: 왼쪽, 1: 오른쪽, 0: 정지)에 따라 paddle을 이동시킨다.
+    /// 아날로그 입력이라면 그 사이 값으로 느리게 움직인다.
+    /// Context 없이 호출할 수 있는 순수한 이동 로직
+    pub fn step(&mut self, dir: f32, dt: f32) {
+        self.dx = dir * PADDLE_SPEED;
 
         if self.dx < 0. {
             self.x = (self.x + self.dx * dt).max(0.);
         } else if self.dx > 0. {
-            self.x = game::VIRTUAL_WIDTH.min(self.x + self.dx * dt);
+            self.x = (game::VIRTUAL_WIDTH - self.width).min(self.x + self.dx * dt);
+        }
+    }
+
+    /// paddle의 가운데가 target x 위치로 가도록 이동시킨다.
+    /// 한 스텝에 max_speed(초당 픽셀) 보다 빠르게 움직이지는 않는다.
+    pub fn follow(&mut self, target: f32, max_speed: f32, dt: f32) {
+        // 시간이 흐르지 않았으면 속도를 구할 수 없으니 제자리에 둔다.
+        if dt <= 0. {
+            self.dx = 0.;
+            return;
         }
+
+        let distance = target - (self.x + self.width / 2.);
+        self.dx = (distance / dt).clamp(-max_speed, max_speed);
+
+        self.x = (self.x + self.dx * dt)
+            .max(0.)
+            .min(game::VIRTUAL_WIDTH - self.width);
     }
+}
 
-    fn draw(&mut self, ctx: &mut Context, reg: &mut Reg) {
+impl Object for Paddle {
+    fn draw(&self, ctx: &mut Context, reg: &mut Reg) {
         reg.draw_sprite(ctx, PADDLE_FLAG + self.color + self.size, self.x, self.y);
     }
 
@@ -200,7 +311,7 @@ impl Object for Paddle {
         }
         let size = idx & SIZE_MASK;
         if size > 0 {
-            self.size = size;
+            self.set_size(size);
         }
     }
 
@@ -215,8 +326,11 @@ pub struct Ball {
     pub height: f32,
     pub x: f32,
     pub y: f32,
+    // 속도는 초당 픽셀
     pub dx: f32,
     pub dy: f32,
+    // 속도 배율 (파워업으로 느려질 때 사용)
+    pub speed: f32,
 }
 
 impl Ball {
@@ -230,40 +344,87 @@ impl Ball {
             height: 8.,
             dx: 0.,
             dy: 0.,
+            speed: 1.,
         }
     }
 
-    pub fn reset(&mut self) {
-        self.y = game::VIRTUAL_HEIGHT - 40.;
-        self.dx = 0.;
-        self.dy = 0.;
+    pub fn get_color(&self) -> i32 {
+        self.color
     }
 
-    pub fn fire(&mut self) {
-        let mut rng = thread_rng();
-        self.dx = rng.gen_range(-4, -2) as f32;
-        self.dy = rng.gen_range(-4, -1) as f32;
+    /// 공을 왼쪽 위 방향으로 발사한다. 속도는 초당 픽셀
+    pub fn fire<R: Rng>(&mut self, rng: &mut R) {
+        self.dx = rng.gen_range(-240., -150.);
+        self.dy = rng.gen_range(-240., -90.);
     }
-}
 
-impl Object for Ball {
-    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, _dt: f32) {
-        if self.x < 0. || self.x > game::VIRTUAL_WIDTH {
-            self.dx = -self.dx;
-            //let sound = reg.get_sound_mut("wall-hit".to_owned()).unwrap();
-            play_sound_once(&"wall-hit".to_owned(), reg);
+    /// 수직 위쪽에서 angle(라디안, 음수면 왼쪽) 만큼 기울어진 방향으로 공을 쏜다.
+    pub fn launch(&mut self, angle: f32) {
+        self.dx = angle.sin() * SERVE_SPEED;
+        self.dy = -angle.cos() * SERVE_SPEED;
+    }
+
+    /// paddle에 맞은 공을 튕겨낸다.
+    /// paddle 가운데에서 멀리 맞을수록, paddle이 움직이는 방향으로 더 기울어진다.
+    /// 공의 빠르기는 그대로 유지한다.
+    pub fn deflect(&mut self, paddle: &Paddle) {
+        let speed = (self.dx * self.dx + self.dy * self.dy).sqrt();
+
+        let half = paddle.width / 2.;
+        let offset = ((self.x + self.width / 2.) - (paddle.x + half)) / half;
+        let angle =
+            offset.clamp(-1., 1.) * MAX_BOUNCE_ANGLE + paddle.dx / PADDLE_SPEED * PADDLE_SPIN_ANGLE;
+        let angle = angle.clamp(-MAX_BOUNCE_ANGLE, MAX_BOUNCE_ANGLE);
+
+        self.dx = speed * angle.sin();
+        self.dy = -speed * angle.cos();
+
+        // paddle에 겹친채로 남지 않도록 위로 올려놓는다.
+        self.y = paddle.y - self.height;
+    }
+
+    /// 겹친 물체 밖으로 공을 밀어낸다.
+    pub fn push_out(&mut self, contact: &Contact) {
+        self.x += contact.normal.0 * contact.depth;
+        self.y += contact.normal.1 * contact.depth;
+    }
+
+    /// 접촉면의 법선 방향으로 공을 튕겨낸다.
+    pub fn bounce(&mut self, normal: (f32, f32)) {
+        if normal.0 != 0. {
+            self.dx = normal.0 * self.dx.abs();
+        }
+        if normal.1 != 0. {
+            self.dy = normal.1 * self.dy.abs();
+        }
+    }
+
+    /// 벽 밖으로 나간 공을 벽 안쪽으로 되돌리고 튕겨낸다.
+    /// 벽에 부딪혔다면 true를 반환한다.
+    pub fn bounce_walls(&mut self) -> bool {
+        let mut wall_hit = false;
+
+        if self.x < 0. {
+            self.x = 0.;
+            self.dx = self.dx.abs();
+            wall_hit = true;
+        } else if self.x + self.width > game::VIRTUAL_WIDTH {
+            self.x = game::VIRTUAL_WIDTH - self.width;
+            self.dx = -self.dx.abs();
+            wall_hit = true;
         }
         if self.y < 0. {
-            self.dy = -self.dy;
-            //let sound = reg.get_sound_mut("wall-hit".to_owned()).unwrap();
-            play_sound_once(&"wall-hit".to_owned(), reg);
+            self.y = 0.;
+            self.dy = self.dy.abs();
+            wall_hit = true;
         }
 
-        self.x += self.dx;
-        self.y += self.dy;
+        wall_hit
     }
+}
 
-    fn draw(&mut self, ctx: &mut Context, reg: &mut Reg) {
+impl Object for Ball {
+    fn draw(&self, ctx: &mut Context, reg: &mut Reg) {
         reg.draw_sprite(ctx, BALL_FLAG + self.color, self.x, self.y);
     }
 
@@ -279,7 +440,16 @@ impl Object for Ball {
     }
 }
 
+/// 블럭 종류
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BlockKind {
+    NORMAL,
+    // 열쇠를 얻기 전에는 깰 수 없는 블럭
+    LOCKED,
+}
+
 pub struct Block {
+    pub kind: BlockKind,
     pub color: i32,
     pub tier: i32,
     pub width: f32,
@@ -295,6 +465,7 @@ impl Block {
     pub fn new(ox: f32, oy: f32) -> Block {
         // Block 설치하기
         Block {
+            kind: BlockKind::NORMAL,
             color: 1,
             tier: 0,
             x: ox,
@@ -307,22 +478,52 @@ impl Block {
         }
     }
 
-    pub fn hit(&mut self, reg: &mut Reg) {
-        self.inplay = false;
-        play_sound(&"brick-hit-2".to_owned(), reg);
+    /// 블럭을 맞췄을 때 얻는 점수
+    /// 단계(tier)가 높고 색상 번호가 클수록 점수가 높다.
+    pub fn points(&self) -> i32 {
+        match self.kind {
+            BlockKind::LOCKED => LOCKED_BLOCK_POINTS,
+            BlockKind::NORMAL => self.tier * 200 + self.color * 25,
+        }
     }
-}
 
-impl Object for Block {
-    fn update(&mut self, _ctx: &mut Context, _reg: &mut Reg, _dt: f32) {
-        ()
+    /// 잠긴 블럭으로 바꾼다. 파티클은 금색으로 나온다.
+    pub fn lock(&mut self) {
+        self.kind = BlockKind::LOCKED;
+        self.color = BLOCK_COLORS;
+        self.tier = BLOCK_TIERS - 1;
     }
 
-    fn draw(&mut self, ctx: &mut Context, reg: &mut Reg) {
+    /// 블럭이 공에 맞았을 때의 처리
+    /// 맞을 때마다 단계(tier)가 하나씩 내려가고, 단계가 0이면 색상이 하나 내려간다.
+    /// 가장 낮은 색상, 단계에서 맞으면 파괴되며 이때 true를 반환한다.
+    /// 잠긴 블럭은 한번에 파괴되므로 열쇠가 있을 때만 호출해야 한다.
+    pub fn hit(&mut self) -> bool {
+        if self.kind == BlockKind::LOCKED {
+            self.inplay = false;
+            true
+        } else if self.tier > 0 {
+            self.tier -= 1;
+            false
+        } else if self.color > 1 {
+            self.color -= 1;
+            false
+        } else {
+            self.inplay = false;
+            true
+        }
+    }
+}
+
+impl Object for Block {
+    fn draw(&self, ctx: &mut Context, reg: &mut Reg) {
         if self.inplay {
             reg.draw_sprite(
                 ctx,
-                BLOCK_FLAG + 1 + (self.color - 1) * 4 + self.tier,
+                match self.kind {
+                    BlockKind::LOCKED => BLOCK_FLAG + LOCKED_BLOCK_SPRITE,
+                    BlockKind::NORMAL => BLOCK_FLAG + 1 + (self.color - 1) * 4 + self.tier,
+                },
                 self.x,
                 self.y,
             );
@@ -340,3 +541,232 @@ impl Object for Block {
         (self.x, self.y, self.width, self.height)
     }
 }
+
+/// 파워업 종류
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PowerUpKind {
+    Grow,
+    Shrink,
+    ExtraLife,
+    SlowBall,
+    ExtraBalls,
+    // 잠긴 블럭을 열 수 있게 한다. 잠긴 블럭을 맞췄을 때만 떨어진다.
+    Key,
+}
+
+impl PowerUpKind {
+    /// 떨어질 수 있는 파워업 목록
+    pub const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::Grow,
+        PowerUpKind::Shrink,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::SlowBall,
+        PowerUpKind::ExtraBalls,
+    ];
+
+    /// 아이콘의 sprite 번호
+    pub fn sprite(self) -> i32 {
+        match self {
+            PowerUpKind::ExtraLife => 2,
+            PowerUpKind::SlowBall => 3,
+            PowerUpKind::Grow => 4,
+            PowerUpKind::Shrink => 5,
+            PowerUpKind::ExtraBalls => 8,
+            PowerUpKind::Key => 9,
+        }
+    }
+
+    /// 효과 지속시간(초), 0이면 즉시 적용되고 끝난다.
+    pub fn duration(self) -> f32 {
+        match self {
+            PowerUpKind::Grow | PowerUpKind::Shrink => 10.,
+            PowerUpKind::SlowBall => 8.,
+            PowerUpKind::ExtraLife | PowerUpKind::ExtraBalls | PowerUpKind::Key => 0.,
+        }
+    }
+}
+
+/// 블럭에서 떨어지는 파워업
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub width: f32,
+    pub height: f32,
+    pub x: f32,
+    pub y: f32,
+    pub dy: f32,
+    pub inplay: bool,
+}
+
+impl PowerUp {
+    /// (cx, cy)를 가운데로 하는 파워업을 만든다.
+    pub fn new(kind: PowerUpKind, cx: f32, cy: f32) -> PowerUp {
+        PowerUp {
+            kind,
+            width: 16.,
+            height: 16.,
+            x: cx - 8.,
+            y: cy - 8.,
+            dy: POWERUP_SPEED,
+            inplay: true,
+        }
+    }
+
+    /// 아래로 떨어뜨린다. 화면 밖으로 나가면 더 이상 쓰이지 않는다.
+    pub fn step(&mut self, dt: f32) {
+        self.y += self.dy * dt;
+
+        if self.y > game::VIRTUAL_HEIGHT {
+            self.inplay = false;
+        }
+    }
+}
+
+impl Object for PowerUp {
+    fn draw(&self, ctx: &mut Context, reg: &mut Reg) {
+        if self.inplay {
+            reg.draw_sprite(ctx, POWERUP_FLAG + self.kind.sprite(), self.x, self.y);
+        }
+    }
+
+    fn set_sprite(&mut self, _idx: i32) {}
+
+    fn get_xywh(&self) -> (f32, f32, f32, f32) {
+        (self.x, self.y, self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (x, y)에 놓인 8x8 공
+    fn ball_at(x: f32, y: f32) -> Ball {
+        let mut ball = Ball::new();
+        ball.x = x;
+        ball.y = y;
+        ball
+    }
+
+    #[test]
+    fn side_overlap_is_an_x_contact() {
+        // 블럭(20, 0, 32x16)의 왼쪽 면에 2픽셀 파고든 공
+        let block = Block::new(20., 0.);
+        let contact = collide_aabb(&ball_at(14., 4.), &block).unwrap();
+
+        assert_eq!(contact.axis, Axis::X);
+        assert_eq!(contact.depth, 2.);
+        assert_eq!(contact.normal, (-1., 0.));
+
+        // 오른쪽 면이라면 오른쪽으로 밀어낸다.
+        let contact = collide_aabb(&ball_at(50., 4.), &block).unwrap();
+        assert_eq!(contact.axis, Axis::X);
+        assert_eq!(contact.depth, 2.);
+        assert_eq!(contact.normal, (1., 0.));
+    }
+
+    #[test]
+    fn vertical_overlap_is_a_y_contact() {
+        let block = Block::new(20., 0.);
+
+        let contact = collide_aabb(&ball_at(30., -6.), &block).unwrap();
+        assert_eq!(contact.axis, Axis::Y);
+        assert_eq!(contact.depth, 2.);
+        assert_eq!(contact.normal, (0., -1.));
+
+        let contact = collide_aabb(&ball_at(30., 14.), &block).unwrap();
+        assert_eq!(contact.axis, Axis::Y);
+        assert_eq!(contact.depth, 2.);
+        assert_eq!(contact.normal, (0., 1.));
+    }
+
+    #[test]
+    fn push_out_leaves_no_overlap() {
+        let block = Block::new(20., 0.);
+
+        for (x, y) in [(14., 4.), (50., 4.), (30., -6.), (30., 14.), (17., -5.)].iter() {
+            let mut ball = ball_at(*x, *y);
+            let contact = collide_aabb(&ball, &block).unwrap();
+
+            ball.push_out(&contact);
+
+            assert_eq!(collide_aabb(&ball, &block), None, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn sweep_finds_entry_time_and_side_normal() {
+        // 블럭 왼쪽 면까지 12픽셀, 24픽셀 이동하면 절반에서 닿는다.
+        let block = Block::new(20., 0.);
+        let hit = sweep_aabb(&ball_at(0., 4.), 24., 0., &block).unwrap();
+
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, (-1., 0.));
+
+        // 오른쪽에서 왼쪽으로 오면 오른쪽 면에 닿는다.
+        let hit = sweep_aabb(&ball_at(60., 4.), -16., 0., &block).unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, (1., 0.));
+    }
+
+    #[test]
+    fn sweep_finds_top_and_bottom_normals() {
+        let block = Block::new(0., 16.);
+
+        // 아래에서 위로 올라가 블럭 아랫면에 닿는다.
+        let hit = sweep_aabb(&ball_at(12., 42.), 0., -20., &block).unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, (0., 1.));
+
+        // 비스듬히 떨어져 윗면에 닿는다. 세로로 먼저 닿으므로 윗면이다.
+        let hit = sweep_aabb(&ball_at(4., 0.), 4., 16., &block).unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, (0., -1.));
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let block = Block::new(20., 0.);
+
+        // 모자라게 이동
+        assert_eq!(sweep_aabb(&ball_at(0., 4.), 10., 0., &block), None);
+        // 반대 방향으로 이동
+        assert_eq!(sweep_aabb(&ball_at(0., 4.), -24., 0., &block), None);
+        // 블럭 아래로 지나감
+        assert_eq!(sweep_aabb(&ball_at(0., 20.), 60., 0., &block), None);
+        // 움직이지 않음
+        assert_eq!(sweep_aabb(&ball_at(0., 4.), 0., 0., &block), None);
+    }
+
+    #[test]
+    fn sweep_ignores_boxes_that_already_overlap() {
+        let block = Block::new(20., 0.);
+        let ball = ball_at(24., 4.);
+        assert!(collide_aabb(&ball, &block).is_some());
+
+        assert_eq!(sweep_aabb(&ball, 10., 0., &block), None);
+        assert_eq!(sweep_aabb(&ball, 0., -10., &block), None);
+    }
+
+    #[test]
+    fn paddle_follow_stays_put_without_time() {
+        let mut paddle = Paddle::new();
+        paddle.dx = 100.;
+        let x = paddle.x;
+
+        paddle.follow(0., 600., 0.);
+
+        assert_eq!(paddle.x, x);
+        assert_eq!(paddle.dx, 0.);
+    }
+
+    #[test]
+    fn paddle_follow_is_capped_by_max_speed() {
+        let mut paddle = Paddle::new();
+        let x = paddle.x;
+
+        paddle.follow(0., 600., 0.1);
+
+        assert_eq!(paddle.dx, -600.);
+        assert_eq!(paddle.x, x - 60.);
+    }
+}
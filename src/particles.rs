@@ -0,0 +1,120 @@
+//! 파티클 시스템
+//! 한 위치에서 여러 개의 파티클을 한번에 뿜어내고,
+//! 각 파티클은 수명동안 가속하며 색상이 서서히 바뀐다.
+
+use ggez::graphics::{self, Color, DrawParam, Image};
+use ggez::nalgebra as na;
+use ggez::Context;
+use rand::Rng;
+
+struct Particle {
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+    ax: f32,
+    ay: f32,
+    life: f32,
+    lifetime: f32,
+    from: Color,
+    to: Color,
+}
+
+pub struct ParticleSystem {
+    // 파티클의 시작 색상과 끝 색상
+    pub colors: (Color, Color),
+    // 파티클 수명 (최소, 최대) 초
+    pub lifetime: (f32, f32),
+    // 뿜어내는 위치에서 흩어지는 범위 (x, y)
+    pub spread: (f32, f32),
+    // 파티클 가속도 범위 ((최소 x, 최소 y), (최대 x, 최대 y))
+    pub acceleration: ((f32, f32), (f32, f32)),
+    // 한번에 뿜어내는 파티클 수
+    pub burst: usize,
+    particles: Vec<Particle>,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> ParticleSystem {
+        ParticleSystem::new()
+    }
+}
+
+impl ParticleSystem {
+    pub fn new() -> ParticleSystem {
+        ParticleSystem {
+            colors: (graphics::WHITE, Color::new(1., 1., 1., 0.)),
+            lifetime: (0.5, 1.0),
+            spread: (10., 10.),
+            acceleration: ((-15., 0.), (15., 80.)),
+            burst: 64,
+            particles: vec![],
+        }
+    }
+
+    /// (x, y) 위치에서 burst 개수만큼 파티클을 뿜어낸다.
+    /// 색상은 현재 설정된 colors를 사용한다.
+    pub fn emit(&mut self, x: f32, y: f32) {
+        let mut rng = rand::thread_rng();
+        let ((min_ax, min_ay), (max_ax, max_ay)) = self.acceleration;
+
+        for _ in 0..self.burst {
+            self.particles.push(Particle {
+                x: x + range(&mut rng, -self.spread.0, self.spread.0),
+                y: y + range(&mut rng, -self.spread.1, self.spread.1),
+                dx: 0.,
+                dy: 0.,
+                ax: range(&mut rng, min_ax, max_ax),
+                ay: range(&mut rng, min_ay, max_ay),
+                life: 0.,
+                lifetime: range(&mut rng, self.lifetime.0, self.lifetime.1),
+                from: self.colors.0,
+                to: self.colors.1,
+            });
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for p in self.particles.iter_mut() {
+            p.life += dt;
+            p.dx += p.ax * dt;
+            p.dy += p.ay * dt;
+            p.x += p.dx * dt;
+            p.y += p.dy * dt;
+        }
+
+        self.particles.retain(|p| p.life < p.lifetime);
+    }
+
+    /// 파티클을 그린다. 이미지의 가운데가 파티클 위치가 된다.
+    pub fn draw(&self, ctx: &mut Context, image: &Image) {
+        for p in self.particles.iter() {
+            let t = (p.life / p.lifetime).min(1.);
+            let color = Color::new(
+                p.from.r + (p.to.r - p.from.r) * t,
+                p.from.g + (p.to.g - p.from.g) * t,
+                p.from.b + (p.to.b - p.from.b) * t,
+                p.from.a + (p.to.a - p.from.a) * t,
+            );
+
+            graphics::draw(
+                ctx,
+                image,
+                DrawParam::new()
+                    .dest(na::Point2::new(p.x, p.y))
+                    .offset(na::Point2::new(0.5, 0.5))
+                    .color(color),
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// [min, max) 범위의 난수. 범위가 비어있으면 min
+fn range<R: Rng>(rng: &mut R, min: f32, max: f32) -> f32 {
+    if max > min {
+        rng.gen_range(min, max)
+    } else {
+        min
+    }
+}
@@ -1,8 +1,8 @@
+use crate::input::InputMap;
 use crate::objects::Object;
 use crate::quad::Quad;
 use ggez;
 use ggez::audio;
-use ggez::input::keyboard::KeyCode;
 use ggez::Context;
 use std::collections::HashMap;
 use std::path::Path;
@@ -12,12 +12,15 @@ pub struct Reg {
     pub fonts: HashMap<String, ggez::graphics::Font>,
     pub images: HashMap<String, ggez::graphics::Image>,
     pub texts: HashMap<String, ggez::graphics::Text>,
-    pub key_status: HashMap<KeyCode, bool>,
+    // 동작별 입력 상태
+    pub input: InputMap,
     pub objects: HashMap<String, Box<dyn Object>>,
     pub f32_values: HashMap<String, f32>,
     pub i32_values: HashMap<String, i32>,
     pub sprites: Option<Quad>,
     pub hearts: Option<Quad>,
+    // 게임 시작시 사용할 난수 시드 (None이면 임의로 정함)
+    pub seed: Option<u64>,
 }
 
 impl Reg {
@@ -27,15 +30,21 @@ impl Reg {
             fonts: HashMap::<String, ggez::graphics::Font>::new(),
             images: HashMap::<String, ggez::graphics::Image>::new(),
             texts: HashMap::<String, ggez::graphics::Text>::new(),
-            key_status: HashMap::<KeyCode, bool>::new(),
+            input: InputMap::new(),
             objects: HashMap::<String, Box<dyn Object>>::new(),
             f32_values: HashMap::<String, f32>::new(),
             i32_values: HashMap::<String, i32>::new(),
             sprites: None,
             hearts: None,
+            seed: None,
         }
     }
 
+    // 난수 시드 지정
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
     // sprites 초기화
     pub fn init_sprite(&mut self, ctx: &mut Context, path: &Path) {
         self.sprites = Some(Quad::new(ctx, path));
@@ -77,27 +86,6 @@ impl Reg {
         self.hearts.as_mut().unwrap().draw_sprite(ctx, key, x, y)
     }
 
-    // 방금 전까지는 안 눌린 것인지 확인
-    // 이후에 눌린 것이라면 해당 값은 true이며
-    // 이제는 해당하는 값에 눌림효과를 넣음
-    pub fn just_pressed(&mut self, key: KeyCode) -> bool {
-        let status = self.key_status.entry(key).or_insert(false);
-
-        if *status == false {
-            *status = true;
-            true
-        } else {
-            false
-        }
-    }
-
-    // 키가 안눌리면 release하기
-    pub fn just_released(&mut self, key: KeyCode) {
-        let status = self.key_status.entry(key).or_insert(false);
-
-        *status = false;
-    }
-
     pub fn add_sound(&mut self, key: String, sound: audio::Source) {
         self.sounds.insert(key, sound);
     }
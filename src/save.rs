@@ -1,38 +1,294 @@
+//! 최고 점수 기록
+//! ggez 파일 시스템의 사용자 디렉토리에 상위 기록을 저장한다.
+//! 파일이 없거나 일부 줄이 깨져 있어도 읽을 수 있는 기록만 사용한다.
+
 use ggez::error::GameResult;
 use ggez::filesystem;
 use ggez::Context;
 use std::io::{Read, Write};
-use std::path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SAVE_FILE: &str = "/highscores.txt";
+
+/// 보관하는 최대 기록 수
+pub const MAX_ENTRIES: usize = 10;
 
-pub const SAVE_FILE: &'static str = "/savefile.txt";
+/// 사용자 이름을 알 수 없을 때 쓰는 이름
+pub const DEFAULT_NAME: &str = "PLAYER";
+
+/// 한 판의 기록
+#[derive(Clone, PartialEq, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: i32,
+    pub level: i32,
+    pub date: String,
+}
+
+impl HighScore {
+    /// 오늘 날짜로 기록을 만든다.
+    pub fn new(name: &str, score: i32, level: i32) -> HighScore {
+        HighScore {
+            // 구분자로 쓰는 탭과 줄바꿈은 이름에 넣지 않는다.
+            name: name.replace(&['\t', '\n', '\r'][..], " "),
+            score,
+            level,
+            date: today(),
+        }
+    }
+
+    /// `이름\t점수\t레벨\t날짜` 형식의 한 줄을 해석한다.
+    /// 이름이 없는 `점수\t레벨\t날짜` 형식은 기본 이름으로 읽는다.
+    fn parse(line: &str) -> Option<HighScore> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (name, fields) = match fields.len() {
+            4 => (fields[0], &fields[1..]),
+            3 => (DEFAULT_NAME, &fields[..]),
+            _ => return None,
+        };
+
+        Some(HighScore {
+            name: name.to_owned(),
+            score: fields[0].trim().parse().ok()?,
+            level: fields[1].trim().parse().ok()?,
+            date: fields[2].trim().to_owned(),
+        })
+    }
+}
 
 pub struct Save {
-    pub contents: Vec<String>,
+    // 점수 내림차순으로 정렬된 기록
+    pub entries: Vec<HighScore>,
+}
+
+impl Default for Save {
+    fn default() -> Save {
+        Save::new()
+    }
 }
 
 impl Save {
     pub fn new() -> Save {
-        Save { contents: vec![] }
+        Save { entries: vec![] }
     }
 
     /// 해당 파일 존재 여부
-    pub fn exists(&self, ctx: &mut Context) -> bool {
-        let file = filesystem::open(ctx, path::Path::new(SAVE_FILE));
+    pub fn exists(ctx: &Context) -> bool {
+        filesystem::exists(ctx, SAVE_FILE)
+    }
 
-        return match file {
-            Ok(_) => true,
-            Err(_) => false,
-        };
+    /// 저장된 기록을 읽는다.
+    /// 파일이 없거나 읽을 수 없으면 빈 기록을 반환한다.
+    pub fn load(ctx: &mut Context) -> Save {
+        if !Save::exists(ctx) {
+            return Save::new();
+        }
+
+        let mut contents = String::new();
+        let read = filesystem::open(ctx, SAVE_FILE)
+            .map_err(|e| e.to_string())
+            .and_then(|mut file| {
+                file.read_to_string(&mut contents)
+                    .map_err(|e| e.to_string())
+            });
+
+        match read {
+            Ok(_) => Save::parse(&contents),
+            Err(_) => Save::new(),
+        }
+    }
+
+    /// 파일 내용을 해석한다. 해석할 수 없는 줄은 무시한다.
+    pub fn parse(contents: &str) -> Save {
+        let mut save = Save::new();
+
+        for entry in contents.lines().filter_map(HighScore::parse) {
+            save.insert(entry);
+        }
+
+        save
+    }
+
+    /// 기록을 파일에 쓴다.
+    pub fn write(&self, ctx: &mut Context) -> GameResult {
+        let mut file = filesystem::create(ctx, SAVE_FILE)?;
+        for entry in self.entries.iter() {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}",
+                entry.name, entry.score, entry.level, entry.date
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// 주어진 점수가 기록에 들어갈 수 있는지 확인
+    pub fn is_high_score(&self, score: i32) -> bool {
+        if score <= 0 {
+            return false;
+        }
+
+        match self.entries.last() {
+            Some(last) => self.entries.len() < MAX_ENTRIES || score > last.score,
+            None => true,
+        }
+    }
+
+    /// 기록을 점수 순서에 맞게 넣는다.
+    /// 들어간 순위(0부터 시작)를 반환하며, 순위 밖이면 None
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        // 같은 점수라면 먼저 세운 기록이 앞선다.
+        let rank = self
+            .entries
+            .iter()
+            .position(|e| e.score < entry.score)
+            .unwrap_or(self.entries.len());
+
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+
+        Some(rank)
     }
-    /// 강제 초기화 루틴
-    pub fn init(&mut self, ctx: &mut Context) {
-        let options = filesystem::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true);
+}
+
+/// 기록에 쓰는 플레이어 이름
+/// 운영체제의 사용자 이름을 쓰고, 알 수 없으면 DEFAULT_NAME
+pub fn player_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_NAME.to_owned())
+}
+
+/// 오늘 날짜 (UTC, YYYY-MM-DD)
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = (secs / 86_400) as i64;
+
+    // 1970-01-01 로부터의 일 수를 그레고리력 날짜로 바꾼다.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: i32, level: i32) -> HighScore {
+        HighScore {
+            name: "AAA".to_owned(),
+            score,
+            level,
+            date: "2020-01-01".to_owned(),
+        }
+    }
+
+    /// 점수가 100, 90, ... 10 인 꽉 찬 기록
+    fn full_save() -> Save {
+        let mut save = Save::new();
+        for i in 0..MAX_ENTRIES as i32 {
+            save.insert(entry(100 - i * 10, 1));
+        }
+        save
+    }
+
+    #[test]
+    fn parse_skips_corrupt_lines() {
+        let save = Save::parse(
+            "BOB\t300\t3\t2020-01-03\ngarbage\nBOB\t200\tx\t2020-01-02\nA\tB\t1\t2\t3\n\nAMY\t100\t1\t2020-01-01\n",
+        );
+
+        let scores: Vec<i32> = save.entries.iter().map(|e| e.score).collect();
+        assert_eq!(scores, vec![300, 100]);
+        assert_eq!(
+            save.entries[0],
+            HighScore {
+                name: "BOB".to_owned(),
+                score: 300,
+                level: 3,
+                date: "2020-01-03".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_reads_lines_without_a_name() {
+        let save = Save::parse("150\t2\t2020-01-02\n");
+
+        assert_eq!(save.entries.len(), 1);
+        assert_eq!(save.entries[0].name, DEFAULT_NAME);
+        assert_eq!(save.entries[0].score, 150);
+    }
+
+    #[test]
+    fn names_never_contain_separators() {
+        let entry = HighScore::new("A\tB\nC", 10, 1);
+        assert_eq!(entry.name, "A B C");
+    }
+
+    #[test]
+    fn parse_sorts_entries() {
+        let save =
+            Save::parse("A\t10\t1\t2020-01-01\nB\t30\t1\t2020-01-01\nC\t20\t1\t2020-01-01\n");
+
+        let scores: Vec<i32> = save.entries.iter().map(|e| e.score).collect();
+        assert_eq!(scores, vec![30, 20, 10]);
+    }
+
+    #[test]
+    fn ties_keep_the_earlier_entry_first() {
+        let mut save = Save::new();
+        assert_eq!(save.insert(entry(50, 1)), Some(0));
+        assert_eq!(save.insert(entry(50, 2)), Some(1));
+        assert_eq!(save.insert(entry(60, 3)), Some(0));
+
+        let levels: Vec<i32> = save.entries.iter().map(|e| e.level).collect();
+        assert_eq!(levels, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn insert_truncates_at_capacity() {
+        let mut save = full_save();
+        assert_eq!(save.entries.len(), MAX_ENTRIES);
+
+        assert_eq!(save.insert(entry(55, 2)), Some(5));
+        assert_eq!(save.entries.len(), MAX_ENTRIES);
+        assert_eq!(save.entries.last().unwrap().score, 20);
+
+        // 꼴찌와 같은 점수는 먼저 세운 기록에 밀린다.
+        assert_eq!(save.insert(entry(20, 2)), None);
+        assert_eq!(save.entries.len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn high_score_boundary() {
+        let save = full_save();
+        assert!(!save.is_high_score(10));
+        assert!(save.is_high_score(11));
+
+        let mut save = Save::new();
+        assert!(!save.is_high_score(0));
+        assert!(save.is_high_score(1));
 
-        let mut file = filesystem::open_options(ctx, path::Path::new(SAVE_FILE), options).unwrap();
-        let result = format!("{}\n{}\n", "test", "test2");
-        file.write_all(result.as_bytes()).unwrap();
+        save.insert(entry(500, 1));
+        assert!(save.is_high_score(1));
     }
 }
@@ -0,0 +1,755 @@
+//! 게임 시뮬레이션
+//! ggez::Context 나 Reg 없이 게임 한 판을 진행시킨다.
+//! PlayState는 이 World를 감싸서 입력, 렌더링, 사운드만 담당한다.
+
+use crate::game;
+use crate::grid::SpatialGrid;
+use crate::level_maker;
+use crate::objects::{self, Ball, Block, BlockKind, Object, Paddle, PowerUp, PowerUpKind, Sweep};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// 레벨을 깼을 때 받는 레벨당 보너스 점수
+pub const LEVEL_CLEAR_BONUS: i32 = 500;
+
+/// 최대 생명 수
+pub const MAX_HEALTH: i32 = 3;
+
+/// 블럭이 파괴될 때 파워업이 떨어질 기본 확률
+pub const POWER_UP_CHANCE: f64 = 0.1;
+
+/// 공이 느려지는 파워업의 속도 배율
+pub const SLOW_BALL_SPEED: f32 = 0.6;
+
+/// 열쇠 없이 잠긴 블럭을 맞췄을 때 열쇠가 떨어질 확률
+pub const KEY_DROP_CHANCE: f64 = 0.25;
+
+/// 서브할 때 조준할 수 있는 최대 각도 (수직 기준, 라디안)
+pub const MAX_AIM_ANGLE: f32 = std::f32::consts::PI / 3.;
+
+/// 조준 각도가 바뀌는 속도 (초당 라디안)
+pub const AIM_SPEED: f32 = std::f32::consts::PI / 2.;
+
+/// 서브를 기다리는 시간, 지나면 자동으로 서브한다. (초)
+pub const SERVE_TIMEOUT: f32 = 5.;
+
+/// broadphase 격자 한 칸의 크기, 블럭 하나의 크기와 같다.
+const BROADPHASE_CELL: (f32, f32) = (32., 16.);
+
+/// 공 하나가 한 스텝동안 튕길 수 있는 최대 횟수
+/// 좁은 틈에 끼인 공이 한 스텝에 끝없이 튕기지 않도록 제한하며,
+/// 다 쓰면 남은 시간은 충돌 검사 없이 이동한다.
+const MAX_CONTACTS: usize = 4;
+
+/// 멀티볼 파워업으로 늘어나는 공 수
+pub const EXTRA_BALLS: usize = 2;
+
+// 공마다 다른 색상을 쓴다.
+const BALL_COLORS: [i32; 4] = [
+    objects::MAGENTA,
+    objects::BLUE,
+    objects::GREEN,
+    objects::RED,
+];
+
+// paddle 크기 순서
+const PADDLE_SIZES: [i32; 4] = [
+    objects::SMALL,
+    objects::MEDIUM,
+    objects::LARGE,
+    objects::HUGE,
+];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PlayStateMode {
+    READY,
+    GO,
+}
+
+/// 한 스텝 동안의 입력 상태
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Input {
+    // paddle 이동 방향과 세기 (-1: 왼쪽 ~ 1: 오른쪽)
+    pub dir: f32,
+    // 서브 조준 방향 (-1: 왼쪽 ~ 1: 오른쪽)
+    pub aim: f32,
+    // 마우스 모드일 때 paddle 가운데가 따라갈 x 위치와 최고 속도 (초당 픽셀)
+    pub target: Option<f32>,
+    pub max_speed: f32,
+    pub serve: bool,
+}
+
+/// 시뮬레이션 중 발생한 사건
+#[derive(PartialEq, Debug, Clone)]
+pub enum Event {
+    WallHit,
+    PaddleHit,
+    // 맞기 전의 블럭 색상과 단계를 함께 알려준다.
+    BrickHit {
+        index: usize,
+        color: i32,
+        tier: i32,
+        destroyed: bool,
+    },
+    Scored(i32),
+    PowerUpCaught(PowerUpKind),
+    PowerUpExpired(PowerUpKind),
+    // 열쇠 없이 잠긴 블럭을 맞춤
+    LockedHit {
+        index: usize,
+    },
+    LevelCleared,
+    LifeLost,
+    GameOver,
+}
+
+/// 지속시간이 있는 파워업 효과
+#[derive(Debug, Clone, Copy)]
+pub struct Effect {
+    pub kind: PowerUpKind,
+    // 남은 시간 (초)
+    pub remaining: f32,
+}
+
+/// 게임 한 판의 모든 상태
+pub struct World {
+    pub paddle: Paddle,
+    // 화면에 있는 공들, 마지막 공이 나가야 생명을 잃는다.
+    pub balls: Vec<Ball>,
+    // 블럭을 바꿀 때는 broadphase도 함께 바꿔야 하므로 set_blocks로만 바꾼다.
+    blocks: Vec<Block>,
+    pub power_ups: Vec<PowerUp>,
+    pub effects: Vec<Effect>,
+    // 블럭이 파괴될 때 파워업이 떨어질 확률 (0 ~ 1)
+    pub power_up_chance: f64,
+    // 파워업 효과가 없을 때의 paddle 크기
+    pub paddle_size: i32,
+    // 잠긴 블럭을 열 수 있는 열쇠를 가지고 있는지 여부
+    pub has_key: bool,
+    pub score: i32,
+    pub health: i32,
+    pub level: i32,
+    pub mode: PlayStateMode,
+    // READY 상태에서 공을 쏠 각도 (수직 기준, 라디안, 음수면 왼쪽)
+    pub aim: f32,
+    // 자동으로 서브하기까지 남은 시간 (초)
+    pub serve_timer: f32,
+    pub seed: u64,
+    // 레벨 클리어 보너스를 이미 받았는지 여부
+    cleared: bool,
+    // 아직 남아있는 블럭의 broadphase
+    grid: SpatialGrid,
+    // 레벨 생성과 공 발사에 쓰이는 난수 생성기
+    rng: StdRng,
+}
+
+impl World {
+    /// 시드를 받아 World를 만든다.
+    /// 같은 시드와 같은 입력이면 항상 같은 게임이 진행된다.
+    pub fn new(level: i32, seed: u64) -> World {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let blocks = level_maker::create_map(level, &mut rng);
+
+        let mut world = World {
+            paddle: Paddle::new(),
+            balls: vec![Ball::new()],
+            blocks: vec![],
+            power_ups: vec![],
+            effects: vec![],
+            power_up_chance: POWER_UP_CHANCE,
+            paddle_size: objects::MEDIUM,
+            has_key: false,
+            score: 0,
+            health: MAX_HEALTH,
+            level,
+            mode: PlayStateMode::READY,
+            aim: -MAX_AIM_ANGLE / 2.,
+            serve_timer: SERVE_TIMEOUT,
+            seed,
+            cleared: false,
+            grid: SpatialGrid::new(BROADPHASE_CELL.0, BROADPHASE_CELL.1),
+            rng,
+        };
+        world.set_blocks(blocks);
+
+        world
+    }
+
+    /// 블럭 배치를 바꾸고 broadphase를 다시 만든다.
+    pub fn set_blocks(&mut self, blocks: Vec<Block>) {
+        self.blocks = blocks;
+        self.grid.rebuild(&self.blocks, |block| block.inplay);
+    }
+
+    /// 블럭 목록, 파괴된 블럭도 자리를 지킨다.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// 블럭 하나를 옮기고 broadphase에 알린다.
+    pub fn move_block(&mut self, index: usize, x: f32, y: f32) {
+        let block = &mut self.blocks[index];
+        block.x = x;
+        block.y = y;
+        if block.inplay {
+            self.grid.update(index, block.get_xywh());
+        }
+    }
+
+    /// 점수와 생명을 유지한 채 다음 레벨을 준비한다.
+    pub fn next_level(&mut self) {
+        self.level += 1;
+        let blocks = level_maker::create_map(self.level, &mut self.rng);
+        self.set_blocks(blocks);
+        self.cleared = false;
+        self.has_key = false;
+        self.paddle.x = game::VIRTUAL_WIDTH / 2.;
+        self.ready();
+        self.reset_power_ups();
+    }
+
+    /// 새 공 하나를 paddle 위에 올리고 서브를 기다린다.
+    fn ready(&mut self) {
+        self.mode = PlayStateMode::READY;
+        self.aim = -MAX_AIM_ANGLE / 2.;
+        self.serve_timer = SERVE_TIMEOUT;
+        self.balls = vec![Ball::new()];
+    }
+
+    /// 조준을 바꾸다가 서브 입력이 들어오거나 시간이 다 되면 조준한 방향으로 공을 쏜다.
+    fn update_serve(&mut self, input: &Input, dt: f32) {
+        self.aim = (self.aim + input.aim.clamp(-1., 1.) * AIM_SPEED * dt)
+            .clamp(-MAX_AIM_ANGLE, MAX_AIM_ANGLE);
+        self.serve_timer -= dt;
+
+        if input.serve || self.serve_timer <= 0. {
+            self.mode = PlayStateMode::GO;
+            for ball in self.balls.iter_mut() {
+                ball.launch(self.aim);
+            }
+        }
+    }
+
+    /// 떨어지는 파워업과 모든 효과를 없앤다.
+    pub fn reset_power_ups(&mut self) {
+        self.power_ups.clear();
+        self.effects.clear();
+        self.apply_effects();
+    }
+
+    /// 해당 파워업 효과가 지속중인지 확인
+    pub fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// 지금 걸려있는 효과에 맞게 paddle 크기와 공 속도를 다시 정한다.
+    fn apply_effects(&mut self) {
+        let base = PADDLE_SIZES
+            .iter()
+            .position(|size| *size == self.paddle_size)
+            .unwrap_or(1) as i32;
+        let mut idx = base;
+        if self.has_effect(PowerUpKind::Grow) {
+            idx += 1;
+        }
+        if self.has_effect(PowerUpKind::Shrink) {
+            idx -= 1;
+        }
+        let size = PADDLE_SIZES[idx.max(0).min(PADDLE_SIZES.len() as i32 - 1) as usize];
+
+        if self.paddle.get_size() != size {
+            self.paddle.set_size(size);
+            self.paddle.x = self
+                .paddle
+                .x
+                .max(0.)
+                .min(game::VIRTUAL_WIDTH - self.paddle.width);
+        }
+
+        let speed = if self.has_effect(PowerUpKind::SlowBall) {
+            SLOW_BALL_SPEED
+        } else {
+            1.
+        };
+        for ball in self.balls.iter_mut() {
+            ball.speed = speed;
+        }
+    }
+
+    /// 첫번째 공 위치에서 공을 더 발사한다.
+    /// 새 공은 아직 쓰이지 않은 색상을 받으며, 공은 최대 BALL_COLORS 개수까지만 늘어난다.
+    fn add_balls(&mut self, count: usize) {
+        let (x, y) = match self.balls.first() {
+            Some(ball) => (ball.x, ball.y),
+            None => return,
+        };
+
+        for _ in 0..count {
+            let color = match BALL_COLORS
+                .iter()
+                .find(|color| self.balls.iter().all(|ball| ball.get_color() != **color))
+            {
+                Some(color) => *color,
+                None => break,
+            };
+
+            let mut ball = Ball::new();
+            ball.x = x;
+            ball.y = y;
+            ball.set_sprite(color);
+            ball.fire(&mut self.rng);
+            self.balls.push(ball);
+        }
+    }
+
+    /// 받은 파워업의 효과를 적용한다.
+    /// 같은 효과가 이미 있다면 지속시간만 다시 채운다.
+    fn catch_power_up(&mut self, kind: PowerUpKind, events: &mut Vec<Event>) {
+        events.push(Event::PowerUpCaught(kind));
+
+        match kind {
+            PowerUpKind::ExtraLife => self.health = (self.health + 1).min(MAX_HEALTH),
+            PowerUpKind::ExtraBalls => self.add_balls(EXTRA_BALLS),
+            PowerUpKind::Key => self.has_key = true,
+            _ => {
+                let remaining = kind.duration();
+                match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+                    Some(effect) => effect.remaining = remaining,
+                    None => self.effects.push(Effect { kind, remaining }),
+                }
+            }
+        }
+
+        self.apply_effects();
+    }
+
+    /// 파워업을 떨어뜨리고, 받은 파워업을 적용하며, 효과 시간을 줄인다.
+    fn update_power_ups(&mut self, dt: f32, events: &mut Vec<Event>) {
+        let mut caught = Vec::<PowerUpKind>::new();
+        for power_up in self.power_ups.iter_mut() {
+            power_up.step(dt);
+
+            if power_up.inplay && objects::collide_aabb(power_up, &self.paddle).is_some() {
+                power_up.inplay = false;
+                caught.push(power_up.kind);
+            }
+        }
+        self.power_ups.retain(|power_up| power_up.inplay);
+
+        for kind in caught {
+            self.catch_power_up(kind, events);
+        }
+
+        let before = self.effects.len();
+        for effect in self.effects.iter_mut() {
+            effect.remaining -= dt;
+            if effect.remaining <= 0. {
+                events.push(Event::PowerUpExpired(effect.kind));
+            }
+        }
+        self.effects.retain(|effect| effect.remaining > 0.);
+
+        if self.effects.len() != before {
+            self.apply_effects();
+        }
+    }
+
+    /// 게임이 끝났는지 확인
+    pub fn is_over(&self) -> bool {
+        self.health <= 0
+    }
+
+    /// 모든 블럭을 깼는지 확인
+    pub fn is_cleared(&self) -> bool {
+        self.blocks.iter().all(|block| !block.inplay)
+    }
+
+    /// 점수를 더하고 사건을 기록한다.
+    fn add_score(&mut self, points: i32, events: &mut Vec<Event>) {
+        self.score += points;
+        events.push(Event::Scored(points));
+    }
+
+    /// 공이 블럭에 맞았을 때의 처리
+    fn hit_block(&mut self, index: usize, events: &mut Vec<Event>) {
+        let block = &mut self.blocks[index];
+        let (cx, cy) = (block.x + block.width / 2., block.y + block.height / 2.);
+
+        if block.kind == BlockKind::LOCKED && !self.has_key {
+            // 열쇠가 없으면 튕겨내기만 하고, 가끔 열쇠를 떨어뜨린다.
+            events.push(Event::LockedHit { index });
+
+            let key_falling = self
+                .power_ups
+                .iter()
+                .any(|power_up| power_up.kind == PowerUpKind::Key);
+            if !key_falling && self.rng.gen_bool(KEY_DROP_CHANCE) {
+                self.power_ups.push(PowerUp::new(PowerUpKind::Key, cx, cy));
+            }
+            return;
+        }
+
+        let (color, tier) = (block.color, block.tier);
+        let points = block.points();
+        let destroyed = block.hit();
+        if destroyed {
+            self.grid.remove(index);
+        }
+        events.push(Event::BrickHit {
+            index,
+            color,
+            tier,
+            destroyed,
+        });
+        self.add_score(points, events);
+
+        // 파괴된 블럭에서 파워업이 떨어질 수 있다.
+        if destroyed && self.rng.gen_bool(self.power_up_chance) {
+            let kinds = &PowerUpKind::ALL;
+            let kind = kinds[self.rng.gen_range(0, kinds.len())];
+            self.power_ups.push(PowerUp::new(kind, cx, cy));
+        }
+    }
+
+    /// 이미 겹쳐 있는 물체(공 쪽으로 움직인 paddle 등) 밖으로 공을 밀어내고 튕긴다.
+    fn resolve_overlaps(&mut self, index: usize, events: &mut Vec<Event>) {
+        let ball = &mut self.balls[index];
+        if let Some(contact) = objects::collide_aabb(ball, &self.paddle) {
+            ball.push_out(&contact);
+            if contact.normal.1 < 0. && ball.dy > 0. {
+                ball.deflect(&self.paddle);
+                events.push(Event::PaddleHit);
+            } else {
+                ball.bounce(contact.normal);
+            }
+        }
+
+        let nearby = self.grid.query(self.balls[index].get_xywh());
+        for block_index in nearby {
+            let ball = &mut self.balls[index];
+            let block = &self.blocks[block_index];
+            if !block.inplay {
+                continue;
+            }
+            if let Some(contact) = objects::collide_aabb(ball, block) {
+                ball.push_out(&contact);
+                ball.bounce(contact.normal);
+                self.hit_block(block_index, events);
+            }
+        }
+    }
+
+    /// 공을 dt초 만큼 이동시킨다.
+    /// 이동 경로에서 가장 먼저 닿는 paddle이나 블럭에 정확히 붙여 튕기고,
+    /// 남은 시간만큼 다시 이동하므로 한 스텝에 여러번 튕길 수 있다.
+    /// MAX_CONTACTS 번 튕긴 뒤 남은 이동에서 겹친 물체는 다음 스텝의 resolve_overlaps가 처리한다.
+    fn move_ball(&mut self, index: usize, dt: f32, events: &mut Vec<Event>) {
+        self.resolve_overlaps(index, events);
+
+        let mut remaining = dt;
+        let mut contacts = 0;
+
+        loop {
+            let ball = &self.balls[index];
+            let vx = ball.dx * ball.speed * remaining;
+            let vy = ball.dy * ball.speed * remaining;
+            if vx == 0. && vy == 0. {
+                break;
+            }
+
+            // 튕길 수 있는 횟수를 다 썼다면 남은 시간만큼 그대로 이동한다.
+            if contacts >= MAX_CONTACTS {
+                let ball = &mut self.balls[index];
+                ball.x += vx;
+                ball.y += vy;
+                break;
+            }
+            contacts += 1;
+
+            // 이동 경로 전체를 덮는 영역 안의 블럭만 검사한다.
+            let (x, y, w, h) = ball.get_xywh();
+            let nearby =
+                self.grid
+                    .query((x.min(x + vx), y.min(y + vy), w + vx.abs(), h + vy.abs()));
+
+            // 가장 먼저 닿는 물체 (None이면 paddle)
+            let mut earliest: Option<(Sweep, Option<usize>)> =
+                objects::sweep_aabb(ball, vx, vy, &self.paddle).map(|hit| (hit, None));
+            for block_index in nearby {
+                let block = &self.blocks[block_index];
+                if let Some(hit) = objects::sweep_aabb(ball, vx, vy, block) {
+                    if earliest.is_none_or(|(first, _)| hit.time < first.time) {
+                        earliest = Some((hit, Some(block_index)));
+                    }
+                }
+            }
+
+            let ball = &mut self.balls[index];
+            let (hit, target) = match earliest {
+                Some(contact) => contact,
+                None => {
+                    ball.x += vx;
+                    ball.y += vy;
+                    break;
+                }
+            };
+
+            // 닿는 지점까지 이동
+            ball.x += vx * hit.time;
+            ball.y += vy * hit.time;
+            remaining *= 1. - hit.time;
+
+            match target {
+                // paddle 윗면에 맞으면 맞은 위치에 따라 튕겨낸다.
+                None if hit.normal.1 < 0. => {
+                    ball.deflect(&self.paddle);
+                    events.push(Event::PaddleHit);
+                }
+                None => ball.bounce(hit.normal),
+                Some(block_index) => {
+                    ball.bounce(hit.normal);
+                    self.hit_block(block_index, events);
+                }
+            }
+        }
+
+        if self.balls[index].bounce_walls() {
+            events.push(Event::WallHit);
+        }
+    }
+
+    /// 입력과 dt를 받아 한 스텝 진행하고, 그 사이 발생한 사건들을 반환한다.
+    pub fn step(&mut self, input: &Input, dt: f32) -> Vec<Event> {
+        let mut events = Vec::<Event>::new();
+
+        if self.is_over() {
+            return events;
+        }
+
+        if self.mode == PlayStateMode::READY {
+            self.update_serve(input, dt);
+        }
+
+        // paddle 처리
+        match input.target {
+            Some(target) => self.paddle.follow(target, input.max_speed, dt),
+            None => self.paddle.step(input.dir.clamp(-1., 1.), dt),
+        }
+
+        // 공처리
+        for index in 0..self.balls.len() {
+            self.move_ball(index, dt, &mut events);
+        }
+
+        self.update_power_ups(dt, &mut events);
+
+        // 게임 상태가 READY이면 공은 paddle을 따라다녀야한다.
+        if self.mode == PlayStateMode::READY {
+            for ball in self.balls.iter_mut() {
+                ball.x = self.paddle.x + self.paddle.width / 2.;
+            }
+        }
+
+        // 화면 아래로 나간 공은 없앤다.
+        self.balls.retain(|ball| ball.y <= game::VIRTUAL_HEIGHT);
+
+        if self.balls.is_empty() {
+            // 마지막 공까지 나가면 죽음..
+            self.health -= 1;
+            events.push(Event::LifeLost);
+
+            if self.is_over() {
+                events.push(Event::GameOver);
+            } else {
+                self.ready();
+                self.reset_power_ups();
+            }
+
+            return events;
+        }
+
+        // 모든 블럭을 깨면 보너스
+        if !self.cleared && self.is_cleared() {
+            self.cleared = true;
+            self.add_score(LEVEL_CLEAR_BONUS * self.level, &mut events);
+            events.push(Event::LevelCleared);
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 60.;
+
+    /// 블럭 하나만 있는 World, 공은 paddle 가운데에서 수직으로 서브한다.
+    fn world_with_block(x: f32, y: f32) -> World {
+        let mut world = World::new(1, 0);
+        world.set_blocks(vec![Block::new(x, y)]);
+        world.aim = 0.;
+        world
+    }
+
+    fn serve() -> Input {
+        Input {
+            serve: true,
+            ..Input::default()
+        }
+    }
+
+    /// 공이 paddle 위에 올라갈 때까지 한 스텝 기다린 뒤 서브한다.
+    fn serve_ball(world: &mut World) {
+        world.step(&Input::default(), DT);
+        world.step(&serve(), DT);
+        assert_eq!(world.mode, PlayStateMode::GO);
+    }
+
+    /// 조건에 맞는 사건이 나올 때까지 같은 입력으로 진행하고, 그동안 나온 사건을 모두 반환한다.
+    fn run_until(
+        world: &mut World,
+        input: &Input,
+        max_steps: usize,
+        until: impl Fn(&Event) -> bool,
+    ) -> Vec<Event> {
+        let mut events = Vec::<Event>::new();
+        for _ in 0..max_steps {
+            let step = world.step(input, DT);
+            let done = step.iter().any(&until);
+            events.extend(step);
+            if done {
+                return events;
+            }
+        }
+        panic!("event not found in {} steps: {:?}", max_steps, events);
+    }
+
+    /// 서브하고 paddle을 왼쪽 끝으로 빼서 공을 놓친다.
+    fn miss_ball(world: &mut World) -> Vec<Event> {
+        serve_ball(world);
+        let left = Input {
+            dir: -1.,
+            ..Input::default()
+        };
+        run_until(world, &left, 600, |e| *e == Event::LifeLost)
+    }
+
+    #[test]
+    fn missing_the_ball_loses_a_life() {
+        let mut world = world_with_block(0., 16.);
+
+        let events = miss_ball(&mut world);
+
+        assert!(!events.contains(&Event::GameOver));
+        assert_eq!(world.health, MAX_HEALTH - 1);
+        assert_eq!(world.mode, PlayStateMode::READY);
+        assert_eq!(world.balls.len(), 1);
+    }
+
+    #[test]
+    fn losing_the_last_life_ends_the_game() {
+        let mut world = world_with_block(0., 16.);
+        world.health = 1;
+
+        let events = miss_ball(&mut world);
+
+        assert!(events.contains(&Event::GameOver));
+        assert!(world.is_over());
+        assert!(world.step(&serve(), DT).is_empty());
+    }
+
+    #[test]
+    fn ball_hits_brick_above_paddle() {
+        let mut world = world_with_block(232., 32.);
+        world.blocks[0].tier = 1;
+
+        serve_ball(&mut world);
+        let events = run_until(&mut world, &Input::default(), 120, |e| {
+            matches!(e, Event::BrickHit { .. })
+        });
+
+        assert!(events.contains(&Event::BrickHit {
+            index: 0,
+            color: 1,
+            tier: 1,
+            destroyed: false,
+        }));
+        assert!(events.contains(&Event::Scored(225)));
+        assert_eq!(world.blocks[0].tier, 0);
+        assert!(world.balls[0].dy > 0.);
+    }
+
+    #[test]
+    fn breaking_the_last_brick_clears_the_level() {
+        let mut world = world_with_block(232., 32.);
+
+        serve_ball(&mut world);
+        let events = run_until(&mut world, &Input::default(), 120, |e| {
+            *e == Event::LevelCleared
+        });
+
+        assert!(events.contains(&Event::BrickHit {
+            index: 0,
+            color: 1,
+            tier: 0,
+            destroyed: true,
+        }));
+        assert!(events.contains(&Event::Scored(LEVEL_CLEAR_BONUS)));
+        assert_eq!(world.score, 25 + LEVEL_CLEAR_BONUS);
+        assert!(world.is_cleared());
+    }
+
+    // 블럭 (x, y, 색상, 단계, 남아있음), 공 (x, y, dx, dy), 점수
+    type Snapshot = (
+        Vec<(f32, f32, i32, i32, bool)>,
+        Vec<(f32, f32, f32, f32)>,
+        i32,
+    );
+
+    /// 블럭, 공, 점수를 비교할 수 있는 형태로 모은다.
+    fn snapshot(world: &World) -> Snapshot {
+        (
+            world
+                .blocks
+                .iter()
+                .map(|b| (b.x, b.y, b.color, b.tier, b.inplay))
+                .collect(),
+            world.balls.iter().map(|b| (b.x, b.y, b.dx, b.dy)).collect(),
+            world.score,
+        )
+    }
+
+    #[test]
+    fn moved_block_is_found_at_its_new_cell() {
+        let mut world = world_with_block(0., 0.);
+        assert_eq!(world.grid.query((0., 0., 32., 16.)), vec![0]);
+
+        world.move_block(0, 320., 160.);
+
+        assert_eq!(world.blocks()[0].get_xywh(), (320., 160., 32., 16.));
+        assert!(world.grid.query((0., 0., 32., 16.)).is_empty());
+        assert_eq!(world.grid.query((320., 160., 32., 16.)), vec![0]);
+    }
+
+    #[test]
+    fn same_seed_and_input_play_the_same_game() {
+        let mut a = World::new(3, 42);
+        let mut b = World::new(3, 42);
+        assert_eq!(snapshot(&a), snapshot(&b));
+
+        for i in 0..3000 {
+            // paddle을 좌우로 움직이며 가끔 조준을 바꾸고 서브한다.
+            let input = Input {
+                dir: ((i / 40) % 3) as f32 - 1.,
+                aim: if i % 200 < 100 { 1. } else { -1. },
+                serve: i % 90 == 0,
+                ..Input::default()
+            };
+
+            assert_eq!(a.step(&input, DT), b.step(&input, DT), "step {}", i);
+            assert_eq!(snapshot(&a), snapshot(&b), "step {}", i);
+        }
+    }
+}
@@ -1,17 +1,22 @@
 //! states는 다양한 게임내 State를 정의한다.
 //! GameState : 게임 진행 상태
 //! InitState : 초기 시작 상태
+//! PaddleSelectState : paddle 색상 선택 상태
+//! VictoryState : 레벨 클리어 상태
 //! MenuState : 메뉴 상태
 
 use crate::game;
+use crate::input::Action;
 use crate::level_maker;
 use crate::objects::*;
-use crate::objects::{self, Ball, Block, Object, Paddle};
+use crate::objects::{self, Object};
+use crate::particles::ParticleSystem;
 use crate::reg::Reg;
+use crate::save::{self, HighScore, Save};
+use crate::simulation::{Event, Input, PlayStateMode, World, MAX_HEALTH, SERVE_TIMEOUT};
 use ggez::audio;
 use ggez::audio::SoundSource;
 use ggez::graphics::{self, Canvas};
-use ggez::input::keyboard::KeyCode;
 use ggez::nalgebra as na;
 use ggez::Context;
 
@@ -19,12 +24,71 @@ pub enum StateResult {
     PushState(Box<dyn States>),
     PopState,
     Trans(Box<dyn States>),
+    // stack을 모두 비우고 새 state 하나로 다시 시작한다.
+    Reset(Box<dyn States>),
+    // 위에서부터 state를 N개 뺀다.
+    PopN(usize),
+    // 주어진 이름의 state가 맨 위가 될 때까지 뺀다.
+    PopTo(String),
+    // 모든 state를 정리하고 게임을 종료한다.
+    Quit,
     Void,
 }
 
 pub trait States {
+    /// 로그와 StateResult::PopTo 에서 state를 찾을 때 쓰는 이름
+    fn name(&self) -> &str;
+
     fn update(&mut self, ctx: &mut Context, reg: &mut Reg, dt: f32) -> StateResult;
     fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult;
+
+    /// 아래 state 위에 겹쳐 그리는 state인지 여부
+    /// overlay state는 화면을 지우지 않으며, 그 아래 state가 먼저 그려진다.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// state가 stack에 들어갈 때 한 번 호출된다.
+    /// state가 사용할 리소스를 reg에 등록한다.
+    fn on_enter(&mut self, _ctx: &mut Context, _reg: &mut Reg) {}
+
+    /// state가 stack에서 빠질 때 한 번 호출된다.
+    /// reg의 리소스는 모든 state가 함께 쓰므로, 여기서 리소스를 지우면
+    /// 아래 state는 on_resume에서 자신의 리소스를 다시 등록해야 한다.
+    fn on_exit(&mut self, _ctx: &mut Context, _reg: &mut Reg) {}
+
+    /// 다른 state가 위에 올라와 이 state를 가릴 때 호출된다.
+    fn on_pause(&mut self, _ctx: &mut Context, _reg: &mut Reg) {}
+
+    /// 위의 state가 빠져 이 state가 다시 맨 위가 될 때 호출된다.
+    fn on_resume(&mut self, _ctx: &mut Context, _reg: &mut Reg) {}
+}
+
+/// state가 등록한 리소스를 모두 지운다.
+pub fn clear_resources(reg: &mut Reg) {
+    reg.clear_font();
+    reg.clear_image();
+    reg.clear_sound();
+    reg.clear_text();
+}
+
+/// 최고 점수 기록을 확인하고, 새 기록이면 저장한다.
+/// 기록과 들어간 순위(0부터 시작)를 반환하며, 순위 밖이면 None
+fn record_high_score(ctx: &mut Context, score: i32, level: i32) -> (Save, Option<usize>) {
+    let mut save = Save::load(ctx);
+    let rank = if save.is_high_score(score) {
+        save.insert(HighScore::new(&save::player_name(), score, level))
+    } else {
+        None
+    };
+
+    if rank.is_some() {
+        if let Err(e) = save.write(ctx) {
+            println!("failed to save high scores: {}", e);
+        }
+    }
+
+    (save, rank)
 }
 
 pub fn play_sound_once(name: &String, reg: &mut Reg) {
@@ -57,9 +121,21 @@ pub fn stop_sound(name: &String, reg: &mut Reg) {
     }
 }
 
+/// 동작에 연결된 키 이름 목록
+/// 키를 다시 설정할 수 있으므로 안내 문구에는 키 이름을 직접 쓰지 않고 이것을 쓴다.
+pub fn binding_names(reg: &Reg, action: Action) -> String {
+    reg.input
+        .bindings(action)
+        .iter()
+        .filter_map(|binding| binding.name())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum InitStateMenu {
     Start,
+    Controls,
     Exit,
 }
 
@@ -67,77 +143,94 @@ pub struct InitState {
     status: InitStateMenu,
 }
 
+impl Default for InitState {
+    fn default() -> InitState {
+        InitState::new()
+    }
+}
+
 impl InitState {
-    pub fn new(ctx: &mut Context, reg: &mut Reg) -> InitState {
+    pub fn new() -> InitState {
+        InitState {
+            status: InitStateMenu::Start,
+        }
+    }
+
+    /// 화면에 쓰는 리소스를 reg에 등록한다.
+    fn load_resources(&mut self, ctx: &mut Context, reg: &mut Reg) {
         let font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
         let title = ggez::graphics::Text::new(("Break Out", font, 16.0));
         let start_menu = ggez::graphics::Text::new(("start game", font, 12.0));
+        let controls_menu = ggez::graphics::Text::new(("controls", font, 12.0));
         let exit_menu = ggez::graphics::Text::new(("exit", font, 12.0));
 
         reg.add_font("font".to_owned(), font);
         reg.add_text("title".to_owned(), title);
         reg.add_text("start_menu".to_owned(), start_menu);
+        reg.add_text("controls_menu".to_owned(), controls_menu);
         reg.add_text("exit_menu".to_owned(), exit_menu);
-        reg.add_sound(
-            "music".to_owned(),
-            audio::Source::new(ctx, "/music.wav").unwrap(),
-        );
+        // 재생 중인 음악은 처음부터 다시 틀지 않도록 남겨둔다.
+        if reg.get_sound_mut("music".to_owned()).is_none() {
+            reg.add_sound(
+                "music".to_owned(),
+                audio::Source::new(ctx, "/music.wav").unwrap(),
+            );
+        }
 
         init_global_sprite(reg);
-        let state = InitState {
-            status: InitStateMenu::Start,
-        };
-
-        state
     }
 }
 
 // 메뉴 화면
 impl States for InitState {
-    fn update(&mut self, ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
+    fn name(&self) -> &str {
+        "init"
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+    }
+
+    /// 위의 state가 reg를 비웠을 수 있으니 리소스를 다시 등록한다.
+    fn on_resume(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+    }
+
+    fn on_exit(&mut self, _ctx: &mut Context, reg: &mut Reg) {
+        clear_resources(reg);
+    }
+
+    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
         // 음악을 플레이한다.
 
         //let music = reg.get_sound_mut("music".to_owned()).unwrap();
         play_sound(&"music".to_owned(), reg);
 
         // 화살표를 눌러 상태를 변경한다.
-        let pressed_key = ggez::input::keyboard::pressed_keys(ctx);
-
-        if !pressed_key.contains(&KeyCode::Up) {
-            reg.just_released(KeyCode::Up);
-        }
+        if reg.input.just_pressed(Action::MenuUp) {
+            self.status = match self.status {
+                InitStateMenu::Start => InitStateMenu::Exit,
+                InitStateMenu::Controls => InitStateMenu::Start,
+                InitStateMenu::Exit => InitStateMenu::Controls,
+            };
 
-        if !pressed_key.contains(&KeyCode::Down) {
-            reg.just_released(KeyCode::Down);
-        }
-
-        if pressed_key.contains(&KeyCode::Up) && reg.just_pressed(KeyCode::Up)
-            || pressed_key.contains(&KeyCode::Down) && reg.just_pressed(KeyCode::Down)
-        {
-            //just_pressed 인지 확인
-
-            if self.status == InitStateMenu::Exit {
-                self.status = InitStateMenu::Start
-            } else {
-                self.status = InitStateMenu::Exit
-            }
+            StateResult::Void
+        } else if reg.input.just_pressed(Action::MenuDown) {
+            self.status = match self.status {
+                InitStateMenu::Start => InitStateMenu::Controls,
+                InitStateMenu::Controls => InitStateMenu::Exit,
+                InitStateMenu::Exit => InitStateMenu::Start,
+            };
 
             StateResult::Void
-        } else if pressed_key.contains(&KeyCode::Return) {
-            // reg 초기화
-            reg.clear_font();
-            reg.clear_image();
-            reg.clear_sound();
-            reg.clear_text();
-
-            // 수치 정보 등록
+        } else if reg.input.just_pressed(Action::Confirm) && self.status == InitStateMenu::Controls
+        {
+            // 메뉴 화면은 그대로 두고 키 설정 화면을 띄운다.
+            StateResult::PushState(Box::new(ControlsState::new()))
+        } else if reg.input.just_pressed(Action::Confirm) {
             match self.status {
-                InitStateMenu::Start => {
-                    let game_state = PlayState::new(ctx, reg);
-
-                    StateResult::Trans(Box::new(game_state))
-                }
-                InitStateMenu::Exit => StateResult::PopState,
+                InitStateMenu::Start => StateResult::Trans(Box::new(PaddleSelectState::new())),
+                _ => StateResult::Quit,
             }
         } else {
             StateResult::Void
@@ -150,9 +243,10 @@ impl States for InitState {
 
         graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
 
-        // 타이틀 (상단 5%, 각 메뉴 상단에서 85%, 95% 위치)
+        // 타이틀 (상단 5%, 각 메뉴 상단에서 75%, 85%, 95% 위치)
         let title = reg.get_text("title".to_owned()).unwrap();
         let start_menu = reg.get_text("start_menu".to_owned()).unwrap();
+        let controls_menu = reg.get_text("controls_menu".to_owned()).unwrap();
         let exit_menu = reg.get_text("exit_menu".to_owned()).unwrap();
         let span = title.width(ctx) as f32;
         graphics::draw(
@@ -176,12 +270,30 @@ impl States for InitState {
             (
                 na::Point2::new(
                     (game::VIRTUAL_WIDTH - span) / 2.0,
-                    game::VIRTUAL_HEIGHT * 0.85,
+                    game::VIRTUAL_HEIGHT * 0.75,
                 ),
                 0.0,
                 match self.status {
                     InitStateMenu::Start => ggez::graphics::Color::from_rgba(200, 200, 255, 255),
-                    InitStateMenu::Exit => ggez::graphics::Color::from_rgba(255, 255, 255, 255),
+                    _ => ggez::graphics::Color::from_rgba(255, 255, 255, 255),
+                },
+            ),
+        )
+        .unwrap();
+
+        let span = controls_menu.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            controls_menu,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.85,
+                ),
+                0.0,
+                match self.status {
+                    InitStateMenu::Controls => ggez::graphics::Color::from_rgba(200, 200, 255, 255),
+                    _ => ggez::graphics::Color::from_rgba(255, 255, 255, 255),
                 },
             ),
         )
@@ -199,393 +311,133 @@ impl States for InitState {
                 0.0,
                 match self.status {
                     InitStateMenu::Exit => ggez::graphics::Color::from_rgba(200, 200, 255, 255),
-                    InitStateMenu::Start => ggez::graphics::Color::from_rgba(255, 255, 255, 255),
+                    _ => ggez::graphics::Color::from_rgba(255, 255, 255, 255),
                 },
             ),
         )
         .unwrap();
 
-        graphics::present(ctx).unwrap();
-
         ggez::graphics::set_canvas(ctx, None);
         StateResult::Void
     }
 }
 
-pub struct PauseState {}
+// 키 설정 화면의 동작 목록 다음 줄들
+const CONTROLS_MOUSE_ROW: usize = Action::ALL.len();
+const CONTROLS_RESET_ROW: usize = Action::ALL.len() + 1;
 
-impl PauseState {
-    pub fn new() -> PauseState {
-        PauseState {}
-    }
+// 키 설정 화면
+// 동작을 골라 [Confirm]을 누르면 다음에 눌리는 키를 그 동작에 연결한다.
+pub struct ControlsState {
+    // 선택된 줄, 동작 목록 다음은 마우스 paddle 설정과 기본 설정으로 되돌리기
+    selected: usize,
+    message: String,
 }
 
-impl States for PauseState {
-    fn update(&mut self, ctx: &mut Context, _reg: &mut Reg, _dt: f32) -> StateResult {
-        // X가 눌러지면 스테이트 종료
-        if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Q) {
-            StateResult::PopState
-        } else {
-            StateResult::Void
-        }
-    }
-
-    fn render(&mut self, ctx: &mut Context, _reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
-        ggez::graphics::set_canvas(ctx, Some(buffer));
-
-        graphics::clear(ctx, [0.0, 1.0, 0.0, 1.0].into());
-
-        graphics::present(ctx).unwrap();
-
-        ggez::graphics::set_canvas(ctx, None);
-        StateResult::Void
+impl Default for ControlsState {
+    fn default() -> ControlsState {
+        ControlsState::new()
     }
 }
 
-#[derive(PartialEq)]
-pub enum PlayStateMode {
-    READY,
-    GO,
-}
-
-pub struct PlayState {
-    paused: bool,
-    paddle: Paddle,
-    ball: Ball,
-    blocks: Vec<Block>,
-    score: i32,
-    health: i32,
-    level: i32,
-    mode: PlayStateMode,
-}
-
-impl PlayState {
-    pub fn new(ctx: &mut Context, reg: &mut Reg) -> PlayState {
-        let default_font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
-
-        reg.add_font("default".to_owned(), default_font);
-
-        let paddle = Paddle::new();
-
-        let ball = Ball::new();
-
-        // 배경 음악
-        reg.add_sound(
-            "music".to_owned(),
-            audio::Source::new(ctx, "/music.wav").unwrap(),
-        );
-
-        // 효과음
-        reg.add_sound(
-            "paddle-hit".to_owned(),
-            audio::Source::new(ctx, "/paddle_hit.wav").unwrap(),
-        );
-
-        reg.add_sound(
-            "score".to_owned(),
-            audio::Source::new(ctx, "/score.wav").unwrap(),
-        );
-
-        reg.add_sound(
-            "wall-hit".to_owned(),
-            audio::Source::new(ctx, "/wall_hit.wav").unwrap(),
-        );
-
-        reg.add_sound(
-            "brick-hit-1".to_owned(),
-            audio::Source::new(ctx, "/brick-hit-1.wav").unwrap(),
-        );
-
-        reg.add_sound(
-            "brick-hit-2".to_owned(),
-            audio::Source::new(ctx, "/brick-hit-2.wav").unwrap(),
-        );
-
-        reg.add_sound(
-            "hurt".to_owned(),
-            audio::Source::new(ctx, "/hurt.wav").unwrap(),
-        );
-
-        reg.add_sound(
-            "victory".to_owned(),
-            audio::Source::new(ctx, "/victory.wav").unwrap(),
-        );
-        reg.add_sound(
-            "recover".to_owned(),
-            audio::Source::new(ctx, "/recover.wav").unwrap(),
-        );
-        reg.add_sound(
-            "high-score".to_owned(),
-            audio::Source::new(ctx, "/high_score.wav").unwrap(),
-        );
-        reg.add_sound(
-            "pause".to_owned(),
-            audio::Source::new(ctx, "/pause.wav").unwrap(),
-        );
-
-        play_bgm(&"music".to_owned(), reg);
-
-        // 블럭 초기화하기
-        let blocks = level_maker::create_map(1);
-
-        // score, health, level 값 가져오기
-        reg.add_i32("score".to_owned(), 0);
-        reg.add_i32("health".to_owned(), 3);
-
-        PlayState {
-            paused: false,
-            paddle,
-            ball,
-            blocks,
-            health: 3,
-            level: 1,
-            score: 0,
-            mode: PlayStateMode::READY,
+impl ControlsState {
+    pub fn new() -> ControlsState {
+        ControlsState {
+            selected: 0,
+            message: String::new(),
         }
     }
-}
-impl States for PlayState {
-    fn update(&mut self, ctx: &mut Context, reg: &mut Reg, dt: f32) -> StateResult {
-        // 나중에 지울거임..
-        // 키 입력할 때 크기 바꿀라구..
-        let color: i32 = if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key1) {
-            objects::BLUE
-        } else if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key2) {
-            objects::RED
-        } else if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key3) {
-            objects::GREEN
-        } else if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key4) {
-            objects::MAGENTA
-        } else {
-            0
-        };
-
-        // 나중에 지울꺼임..
-        // 키 입력할 때 색상 바꿀라구..
-        let size: i32 = if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key5) {
-            objects::SMALL
-        } else if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key6) {
-            objects::MEDIUM
-        } else if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key7) {
-            objects::LARGE
-        } else if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Key8) {
-            objects::HUGE
-        } else {
-            0
-        };
 
-        if self.mode == PlayStateMode::READY
-            && ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Space)
-        {
-            self.mode = PlayStateMode::GO;
-            self.ball.fire();
+    /// 바뀐 설정을 파일에 저장한다.
+    fn save(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        if let Err(e) = reg.input.write(ctx) {
+            println!("failed to save controls: {}", e);
         }
-        if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::X) {
-            reg.clear_font();
-            StateResult::PopState
-        } else {
-            if self.paused == false {
-                if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::P) {
-                    self.paused = true;
-
-                    //let music = reg.get_sound_mut("music".to_owned()).unwrap();
-                    stop_sound(&("music".to_owned()), reg);
-
-                    //let sound = reg.get_sound_mut("pause".to_owned()).unwrap();
-                    play_sound_once(&("pause".to_owned()), reg);
-                }
-
-                // paddle 처리
-                if color != 0 || size != 0 {
-                    self.paddle.set_sprite(objects::PADDLE_FLAG + color + size);
-                }
-                self.paddle.update(ctx, reg, dt);
-
-                // 공처리
-                self.ball.update(ctx, reg, dt);
-
-                // 게임 상태가 READY이면 공은 paddle을 따라다녀야한다.
-                if self.mode == PlayStateMode::READY {
-                    self.ball.x = self.paddle.x + self.paddle.width / 2.;
-                }
-
-                if self.ball.y > game::VIRTUAL_HEIGHT {
-                    // 죽음..
-                    self.health = self.health - 1;
-
-                    if self.health <= 0 {
-                        reg.clear_font();
-                        reg.clear_image();
-                        reg.clear_sound();
-                        reg.clear_text();
+    }
+}
 
-                        let end_state = EndState::new(ctx, reg);
+impl States for ControlsState {
+    fn name(&self) -> &str {
+        "controls"
+    }
 
-                        StateResult::Trans(Box::new(end_state))
+    fn update(&mut self, ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
+        // 키 입력을 받는 중
+        if reg.input.is_capturing() {
+            if let Some(binding) = reg.input.captured() {
+                let action = Action::ALL[self.selected];
+
+                // Back에 연결된 키를 누르면 입력 받기를 취소한다.
+                self.message = if action != Action::Back
+                    && reg.input.bindings(Action::Back).contains(&binding)
+                {
+                    "Cancelled".to_owned()
+                } else if let Some(name) = binding.name() {
+                    if let Some(other) = reg.input.conflict(action, binding) {
+                        format!("{} is already used by {}", name, other.label())
                     } else {
-                        self.mode = PlayStateMode::READY;
-                        self.ball.reset();
-                        StateResult::Void
+                        reg.input.rebind(action, binding);
+                        self.save(ctx, reg);
+                        format!("{} set to {}", action.label(), name)
                     }
                 } else {
-                    // 두 물체의 충돌처리
-                    let collide = objects::collide_aabb(&self.paddle, &self.ball);
-                    if collide.contains(&CollideFlag::TOP) {
-                        self.ball.dy = -self.ball.dy;
-                        //let sound = reg.get_sound_mut("paddle-hit".to_owned()).unwrap();
-                        play_sound_once(&("paddle-hit".to_owned()), reg);
-                    }
-
-                    // 블럭하고 충돌처리
-                    for block in self.blocks.iter_mut() {
-                        if block.inplay == true {
-                            let collide = objects::collide_aabb(&self.ball, block);
-                            if collide.len() > 0 {
-                                block.hit(reg);
-
-                                // 공 상단 / 하단
-                                if collide.contains(&CollideFlag::TOP) && self.ball.dy < 0.
-                                    || collide.contains(&CollideFlag::BOTTOM) && self.ball.dy > 0.
-                                {
-                                    self.ball.dy = -self.ball.dy;
-                                }
-                                // 공 좌측 / 우측
-                                if collide.contains(&CollideFlag::LEFT) && self.ball.dx < 0.
-                                    || collide.contains(&CollideFlag::RIGHT) && self.ball.dx > 0.
-                                {
-                                    self.ball.dx = -self.ball.dx;
-                                }
-                            }
-                        }
-                    }
-
-                    StateResult::Void
-                }
-            } else {
-                if ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Return) {
-                    self.paused = false;
-                    //let music = reg.get_sound_mut("music".to_owned()).unwrap();
-                    //music.set_repeat(true);
-                    stop_sound(&("music".to_owned()), reg);
-                }
-
-                StateResult::Void
+                    "That key cannot be bound".to_owned()
+                };
             }
-        }
-    }
-
-    fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
-        graphics::set_canvas(ctx, Some(buffer));
-
-        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
-
-        for block in self.blocks.iter_mut() {
-            block.draw(ctx, reg);
-        }
-
-        self.paddle.draw(ctx, reg);
-
-        self.ball.draw(ctx, reg);
-        if self.paused == true {
-            let message = ggez::graphics::Text::new((
-                "Game Paused\n\nPress [Enter] To Resume",
-                *reg.get_font("default".to_owned()).unwrap(),
-                16.0,
-            ));
-
-            let span = message.width(ctx) as f32;
-            graphics::draw(
-                ctx,
-                &message,
-                (
-                    na::Point2::new(
-                        (game::VIRTUAL_WIDTH - span) / 2.0,
-                        game::VIRTUAL_HEIGHT / 2.0,
-                    ),
-                    0.0,
-                    graphics::WHITE,
-                ),
-            )
-            .unwrap();
-        }
-
-        // 생명 출력하기
-        let health = self.health;
 
-        let mut hx = 0.;
-
-        for _ in 0..health {
-            reg.draw_heart(ctx, objects::HEARTS_FLAG, hx, 0.);
-            hx = hx + 11.;
+            return StateResult::Void;
         }
 
-        for _ in health..3 {
-            reg.draw_heart(ctx, objects::HEARTS_FLAG + 1, hx, 0.);
-            hx = hx + 11.;
-        }
-
-        graphics::present(ctx).unwrap();
-
-        graphics::set_canvas(ctx, None);
-
-        StateResult::Void
-    }
-}
-
-// 게임 종료화면
-pub struct EndState {}
-
-impl EndState {
-    pub fn new(ctx: &mut Context, reg: &mut Reg) -> EndState {
-        let score = reg.get_i32("score".to_owned());
-        let font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
-        let title = ggez::graphics::Text::new((format!("Your Score is {} ", score), font, 16.0));
-        let start_menu = ggez::graphics::Text::new(("Push [Spage] To Return", font, 12.0));
-
-        reg.add_font("font".to_owned(), font);
-        reg.add_text("title".to_owned(), title);
-        reg.add_text("start_menu".to_owned(), start_menu);
-
-        init_global_sprite(reg);
-        let state = EndState {};
-
-        state
-    }
-}
-
-// 메뉴 화면
-impl States for EndState {
-    fn update(&mut self, ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
-        // 화살표를 눌러 상태를 변경한다.
-        let pressed_key = ggez::input::keyboard::pressed_keys(ctx);
-
-        if pressed_key.contains(&KeyCode::Space) {
-            // reg 초기화
-            reg.clear_font();
-            reg.clear_image();
-            reg.clear_sound();
-            reg.clear_text();
-
-            let init_state = InitState::new(ctx, reg);
-            StateResult::Trans(Box::new(init_state))
+        let rows = CONTROLS_RESET_ROW + 1;
+        if reg.input.just_pressed(Action::MenuUp) {
+            self.selected = (self.selected + rows - 1) % rows;
+            StateResult::Void
+        } else if reg.input.just_pressed(Action::MenuDown) {
+            self.selected = (self.selected + 1) % rows;
+            StateResult::Void
+        } else if reg.input.just_pressed(Action::Confirm) {
+            if self.selected == CONTROLS_RESET_ROW {
+                reg.input.reset_bindings();
+                self.save(ctx, reg);
+                self.message = "Controls reset to defaults".to_owned();
+            } else if self.selected == CONTROLS_MOUSE_ROW {
+                reg.input.mouse_paddle = !reg.input.mouse_paddle;
+                self.save(ctx, reg);
+                self.message = String::new();
+            } else {
+                let action = Action::ALL[self.selected];
+                reg.input.capture();
+                self.message = if action == Action::Back {
+                    format!("Press a key for {}", action.label())
+                } else {
+                    format!(
+                        "Press a key for {}, {} to cancel",
+                        action.label(),
+                        binding_names(reg, Action::Back)
+                    )
+                };
+            }
+            StateResult::Void
+        } else if reg.input.just_pressed(Action::Back) {
+            StateResult::PopState
         } else {
             StateResult::Void
         }
     }
 
-    /// 모든 Render는 이제 자체에 포함된 buffer에만 그린다.
     fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
         ggez::graphics::set_canvas(ctx, Some(buffer));
 
         graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
 
-        // 타이틀 (상단 5%, 각 메뉴 상단에서 85%, 95% 위치)
-        let title = reg.get_text("title".to_owned()).unwrap();
-        let start_menu = reg.get_text("start_menu".to_owned()).unwrap();
+        let font = *reg.get_font("font".to_owned()).unwrap();
+        let selected_color = ggez::graphics::Color::from_rgba(200, 200, 255, 255);
+
+        let title = ggez::graphics::Text::new(("Controls", font, 16.0));
         let span = title.width(ctx) as f32;
         graphics::draw(
             ctx,
-            title,
+            &title,
             (
                 na::Point2::new(
                     (game::VIRTUAL_WIDTH - span) / 2.0,
@@ -597,22 +449,1078 @@ impl States for EndState {
         )
         .unwrap();
 
-        let span = start_menu.width(ctx) as f32;
-        graphics::draw(
-            ctx,
-            start_menu,
-            (
-                na::Point2::new(
-                    (game::VIRTUAL_WIDTH - span) / 2.0,
-                    game::VIRTUAL_HEIGHT * 0.85,
-                ),
-                0.0,
-                ggez::graphics::Color::from_rgba(200, 200, 255, 255),
-            ),
-        )
-        .unwrap();
+        // 동작별 키 목록 (왼쪽 동작 이름, 오른쪽 키)
+        let mut y = game::VIRTUAL_HEIGHT * 0.16;
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let color = if i == self.selected {
+                selected_color
+            } else {
+                ggez::graphics::WHITE
+            };
 
-        graphics::present(ctx).unwrap();
+            let label = ggez::graphics::Text::new((action.label(), font, 8.0));
+            graphics::draw(
+                ctx,
+                &label,
+                (na::Point2::new(game::VIRTUAL_WIDTH * 0.25, y), 0.0, color),
+            )
+            .unwrap();
+
+            let keys = if i == self.selected && reg.input.is_capturing() {
+                "...".to_owned()
+            } else {
+                binding_names(reg, *action)
+            };
+            let keys = ggez::graphics::Text::new((keys, font, 8.0));
+            graphics::draw(
+                ctx,
+                &keys,
+                (na::Point2::new(game::VIRTUAL_WIDTH * 0.55, y), 0.0, color),
+            )
+            .unwrap();
+
+            y += 12.;
+        }
+
+        let paddle = ggez::graphics::Text::new((
+            format!(
+                "Paddle: {}",
+                if reg.input.mouse_paddle {
+                    "Mouse"
+                } else {
+                    "Keys"
+                }
+            ),
+            font,
+            8.0,
+        ));
+        graphics::draw(
+            ctx,
+            &paddle,
+            (
+                na::Point2::new(game::VIRTUAL_WIDTH * 0.25, y),
+                0.0,
+                if self.selected == CONTROLS_MOUSE_ROW {
+                    selected_color
+                } else {
+                    ggez::graphics::WHITE
+                },
+            ),
+        )
+        .unwrap();
+        y += 12.;
+
+        let reset = ggez::graphics::Text::new(("reset to defaults", font, 8.0));
+        graphics::draw(
+            ctx,
+            &reset,
+            (
+                na::Point2::new(game::VIRTUAL_WIDTH * 0.25, y),
+                0.0,
+                if self.selected == CONTROLS_RESET_ROW {
+                    selected_color
+                } else {
+                    ggez::graphics::WHITE
+                },
+            ),
+        )
+        .unwrap();
+
+        let message = ggez::graphics::Text::new((self.message.as_str(), font, 8.0));
+        let span = message.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            &message,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.85,
+                ),
+                0.0,
+                ggez::graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        let help = ggez::graphics::Text::new((
+            format!(
+                "[{}] Change  [{}] Back",
+                binding_names(reg, Action::Confirm),
+                binding_names(reg, Action::Back)
+            ),
+            font,
+            8.0,
+        ));
+        let span = help.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            &help,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.93,
+                ),
+                0.0,
+                selected_color,
+            ),
+        )
+        .unwrap();
+
+        ggez::graphics::set_canvas(ctx, None);
+        StateResult::Void
+    }
+}
+
+// 일시 정지 화면
+// 게임 화면 위에 겹쳐 그리며, 아래의 PlayState는 멈춰 있다.
+pub struct PauseState {}
+
+impl Default for PauseState {
+    fn default() -> PauseState {
+        PauseState::new()
+    }
+}
+
+impl PauseState {
+    pub fn new() -> PauseState {
+        PauseState {}
+    }
+}
+
+impl States for PauseState {
+    fn name(&self) -> &str {
+        "pause"
+    }
+
+    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
+        // Confirm이나 일시 정지 키가 눌러지면 게임으로 돌아간다.
+        if reg.input.just_pressed(Action::Confirm) || reg.input.just_pressed(Action::Pause) {
+            StateResult::PopState
+        } else {
+            StateResult::Void
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
+        ggez::graphics::set_canvas(ctx, Some(buffer));
+
+        // 게임 화면을 어둡게 덮는다.
+        let shade = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(0., 0., game::VIRTUAL_WIDTH, game::VIRTUAL_HEIGHT),
+            graphics::Color::from_rgba(0, 0, 0, 160),
+        )
+        .unwrap();
+        graphics::draw(ctx, &shade, graphics::DrawParam::new()).unwrap();
+
+        let message = ggez::graphics::Text::new((
+            format!(
+                "Game Paused\n\nPress [{}] To Resume",
+                binding_names(reg, Action::Confirm)
+            ),
+            *reg.get_font("default".to_owned()).unwrap(),
+            16.0,
+        ));
+
+        let span = message.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            &message,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT / 2.0,
+                ),
+                0.0,
+                graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        ggez::graphics::set_canvas(ctx, None);
+        StateResult::Void
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+// 서브 조준 점선의 점 수와 간격 (픽셀)
+const SERVE_GUIDE_DOTS: usize = 6;
+const SERVE_GUIDE_SPACING: f32 = 8.;
+
+// paddle 선택 화면에서 고를 수 있는 색상
+const PADDLE_COLORS: [i32; 4] = [
+    objects::BLUE,
+    objects::GREEN,
+    objects::RED,
+    objects::MAGENTA,
+];
+
+// paddle 색상 선택 화면
+// 좌우로 색상을 고르고 [Confirm]을 누르면 게임을 시작한다.
+pub struct PaddleSelectState {
+    // PADDLE_COLORS 중 선택된 색상
+    selected: usize,
+}
+
+impl Default for PaddleSelectState {
+    fn default() -> PaddleSelectState {
+        PaddleSelectState::new()
+    }
+}
+
+impl PaddleSelectState {
+    pub fn new() -> PaddleSelectState {
+        PaddleSelectState { selected: 0 }
+    }
+
+    /// arrows.png 의 왼쪽(0) 또는 오른쪽(1) 화살표를 그린다.
+    /// 더 이상 움직일 수 없는 방향은 어둡게 그린다.
+    fn draw_arrow(
+        ctx: &mut Context,
+        arrows: &graphics::Image,
+        index: f32,
+        x: f32,
+        y: f32,
+        enabled: bool,
+    ) {
+        let color = if enabled {
+            graphics::WHITE
+        } else {
+            graphics::Color::from_rgba(40, 40, 40, 128)
+        };
+
+        graphics::draw(
+            ctx,
+            arrows,
+            graphics::DrawParam::new()
+                .src(graphics::Rect::new(index * 0.5, 0., 0.5, 1.))
+                .dest(na::Point2::new(x, y))
+                .color(color),
+        )
+        .unwrap();
+    }
+
+    /// 화면에 쓰는 리소스를 reg에 등록한다.
+    fn load_resources(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        let font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
+        let title =
+            ggez::graphics::Text::new(("Select your paddle with left and right!", font, 12.0));
+        let start_menu = ggez::graphics::Text::new((
+            format!(
+                "Press [{}] To Continue",
+                binding_names(reg, Action::Confirm)
+            ),
+            font,
+            12.0,
+        ));
+
+        reg.add_font("font".to_owned(), font);
+        reg.add_text("title".to_owned(), title);
+        reg.add_text("start_menu".to_owned(), start_menu);
+        reg.add_image(
+            "arrows".to_owned(),
+            ggez::graphics::Image::new(ctx, "/arrows.png").unwrap(),
+        );
+        reg.add_sound(
+            "select".to_owned(),
+            audio::Source::new(ctx, "/select.wav").unwrap(),
+        );
+        reg.add_sound(
+            "no-select".to_owned(),
+            audio::Source::new(ctx, "/no-select.wav").unwrap(),
+        );
+    }
+}
+
+impl States for PaddleSelectState {
+    fn name(&self) -> &str {
+        "paddle_select"
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+    }
+
+    /// 위의 state가 reg를 비웠을 수 있으니 리소스를 다시 등록한다.
+    fn on_resume(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+    }
+
+    fn on_exit(&mut self, _ctx: &mut Context, reg: &mut Reg) {
+        clear_resources(reg);
+    }
+
+    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
+        if reg.input.just_pressed(Action::MoveLeft) {
+            if self.selected == 0 {
+                play_sound(&"no-select".to_owned(), reg);
+            } else {
+                self.selected -= 1;
+                play_sound(&"select".to_owned(), reg);
+            }
+            StateResult::Void
+        } else if reg.input.just_pressed(Action::MoveRight) {
+            if self.selected == PADDLE_COLORS.len() - 1 {
+                play_sound(&"no-select".to_owned(), reg);
+            } else {
+                self.selected += 1;
+                play_sound(&"select".to_owned(), reg);
+            }
+            StateResult::Void
+        } else if reg.input.just_pressed(Action::Confirm) {
+            let play_state = PlayState::new(reg, PADDLE_COLORS[self.selected]);
+            StateResult::Trans(Box::new(play_state))
+        } else if reg.input.just_pressed(Action::Back) {
+            StateResult::Reset(Box::new(InitState::new()))
+        } else {
+            StateResult::Void
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
+        ggez::graphics::set_canvas(ctx, Some(buffer));
+
+        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
+
+        let title = reg.get_text("title".to_owned()).unwrap();
+        let span = title.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            title,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT / 4.0,
+                ),
+                0.0,
+                ggez::graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        let start_menu = reg.get_text("start_menu".to_owned()).unwrap();
+        let span = start_menu.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            start_menu,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT / 3.0,
+                ),
+                0.0,
+                ggez::graphics::Color::from_rgba(200, 200, 255, 255),
+            ),
+        )
+        .unwrap();
+
+        // paddle 양옆에 화살표 (화살표는 24x24)
+        let y = game::VIRTUAL_HEIGHT - game::VIRTUAL_HEIGHT / 3.;
+        let arrows = reg.get_image("arrows".to_owned()).unwrap();
+        PaddleSelectState::draw_arrow(
+            ctx,
+            arrows,
+            0.,
+            game::VIRTUAL_WIDTH / 4. - 24.,
+            y,
+            self.selected > 0,
+        );
+        PaddleSelectState::draw_arrow(
+            ctx,
+            arrows,
+            1.,
+            game::VIRTUAL_WIDTH - game::VIRTUAL_WIDTH / 4.,
+            y,
+            self.selected < PADDLE_COLORS.len() - 1,
+        );
+
+        // 선택된 paddle (64x16)
+        reg.draw_sprite(
+            ctx,
+            objects::PADDLE_FLAG + PADDLE_COLORS[self.selected] + objects::MEDIUM,
+            game::VIRTUAL_WIDTH / 2. - 32.,
+            y + 4.,
+        );
+
+        ggez::graphics::set_canvas(ctx, None);
+        StateResult::Void
+    }
+}
+
+pub struct PlayState {
+    // 진행중인 게임, 다음 state로 넘겨준 뒤에는 None
+    world: Option<World>,
+    particles: ParticleSystem,
+    // 레벨 파일에 적힌 레벨 이름, 없으면 빈 문자열
+    level_name: String,
+    // 레벨 파일에 적힌 배경 음악과 배경 이미지 경로, 읽을 수 없으면 None
+    music: Option<String>,
+    background: Option<String>,
+}
+
+/// 게임 화면의 효과음 이름과 파일
+const PLAY_SOUNDS: [(&str, &str); 11] = [
+    ("paddle-hit", "/paddle_hit.wav"),
+    ("score", "/score.wav"),
+    ("wall-hit", "/wall_hit.wav"),
+    ("brick-hit-1", "/brick-hit-1.wav"),
+    ("brick-hit-2", "/brick-hit-2.wav"),
+    ("hurt", "/hurt.wav"),
+    ("victory", "/victory.wav"),
+    ("recover", "/recover.wav"),
+    ("high-score", "/high_score.wav"),
+    ("pause", "/pause.wav"),
+    ("power-up", "/confirm.wav"),
+];
+
+/// 블럭 색상별 파티클 색상 (파랑, 초록, 빨강, 보라, 금색)
+fn block_particle_color(color: i32) -> (u8, u8, u8) {
+    match color {
+        1 => (99, 155, 255),
+        2 => (106, 190, 47),
+        3 => (217, 87, 99),
+        4 => (215, 123, 186),
+        _ => (251, 242, 54),
+    }
+}
+
+impl PlayState {
+    /// 선택한 paddle 색상으로 새 게임을 시작한다.
+    pub fn new(reg: &Reg, paddle_color: i32) -> PlayState {
+        // 블럭 초기화하기
+        // 시드가 지정되지 않았다면 임의로 정하고, 재현할 수 있도록 출력한다.
+        let seed = reg.seed.unwrap_or_else(rand::random);
+        println!("seed: {}", seed);
+        let mut world = World::new(1, seed);
+        world
+            .paddle
+            .set_sprite(objects::PADDLE_FLAG + paddle_color + world.paddle_size);
+
+        PlayState::from_world(world)
+    }
+
+    /// 진행중인 게임(점수, 생명, 레벨)을 이어서 시작한다.
+    pub fn from_world(world: World) -> PlayState {
+        PlayState {
+            world: Some(world),
+            particles: ParticleSystem::new(),
+            level_name: String::new(),
+            music: None,
+            background: None,
+        }
+    }
+
+    /// 입력 상태를 시뮬레이션 입력으로 변환한다.
+    fn read_input(reg: &Reg) -> Input {
+        Input {
+            dir: reg.input.value(Action::MoveRight) - reg.input.value(Action::MoveLeft),
+            aim: reg.input.value(Action::AimRight) - reg.input.value(Action::AimLeft),
+            target: reg.input.mouse_target(),
+            max_speed: reg.input.mouse_speed,
+            serve: reg.input.pressed(Action::Serve),
+        }
+    }
+
+    /// 시뮬레이션에서 발생한 사건에 맞는 효과음과 파티클을 재생한다.
+    fn play_events(&mut self, events: &[Event], reg: &mut Reg) {
+        for event in events {
+            if let Event::BrickHit {
+                index, color, tier, ..
+            } = event
+            {
+                // 블럭 색상의 파티클, 단계가 높을수록 진하게
+                let (r, g, b) = block_particle_color(*color);
+                let alpha = 55 * (*tier as u8 + 1);
+                self.particles.colors = (
+                    graphics::Color::from_rgba(r, g, b, alpha),
+                    graphics::Color::from_rgba(r, g, b, 0),
+                );
+
+                let (x, y, w, h) = self.world.as_ref().unwrap().blocks()[*index].get_xywh();
+                self.particles.emit(x + w / 2., y + h / 2.);
+            }
+
+            match event {
+                Event::WallHit => play_sound_once(&"wall-hit".to_owned(), reg),
+                Event::PaddleHit => play_sound_once(&("paddle-hit".to_owned()), reg),
+                // 손상은 brick-hit-1, 파괴는 brick-hit-2
+                Event::BrickHit { destroyed, .. } => {
+                    if *destroyed {
+                        play_sound(&"brick-hit-2".to_owned(), reg)
+                    } else {
+                        play_sound(&"brick-hit-1".to_owned(), reg)
+                    }
+                }
+                Event::LockedHit { .. } => play_sound_once(&"wall-hit".to_owned(), reg),
+                Event::Scored(_) => play_sound_once(&"score".to_owned(), reg),
+                Event::PowerUpCaught(PowerUpKind::ExtraLife) => {
+                    play_sound(&"recover".to_owned(), reg)
+                }
+                Event::PowerUpCaught(_) => play_sound(&"power-up".to_owned(), reg),
+                _ => (),
+            }
+        }
+    }
+
+    /// 게임 화면에 쓰는 리소스를 reg에 등록한다.
+    /// 이미 등록된 리소스는 그대로 두므로, 일시 정지에서 돌아올 때는 지워진 것만 다시 읽는다.
+    /// 레벨 파일에 적힌 배경이나 음악을 읽을 수 없으면 오류를 출력하고 기본값을 쓴다.
+    fn load_resources(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        if reg.get_font("default".to_owned()).is_none() {
+            let default_font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
+            reg.add_font("default".to_owned(), default_font);
+        }
+        if reg.get_image("particle".to_owned()).is_none() {
+            reg.add_image(
+                "particle".to_owned(),
+                ggez::graphics::Image::new(ctx, "/particle.png").unwrap(),
+            );
+        }
+
+        let file = level_maker::level_path(self.world.as_ref().unwrap().level);
+        if reg.get_image("background".to_owned()).is_none() {
+            if let Some(path) = self.background.clone() {
+                match ggez::graphics::Image::new(ctx, &path) {
+                    Ok(image) => reg.add_image("background".to_owned(), image),
+                    Err(e) => {
+                        println!("{}: cannot load background `{}`: {}", file, path, e);
+                        self.background = None;
+                    }
+                }
+            }
+        }
+
+        // 배경 음악
+        if reg.get_sound_mut("music".to_owned()).is_none() {
+            let mut music = None;
+            if let Some(path) = self.music.clone() {
+                match audio::Source::new(ctx, &path) {
+                    Ok(source) => music = Some(source),
+                    Err(e) => {
+                        println!("{}: cannot load music `{}`: {}", file, path, e);
+                        self.music = None;
+                    }
+                }
+            }
+            let music = music.unwrap_or_else(|| audio::Source::new(ctx, "/music.wav").unwrap());
+            reg.add_sound("music".to_owned(), music);
+        }
+
+        // 효과음
+        for (name, path) in PLAY_SOUNDS.iter() {
+            if reg.get_sound_mut((*name).to_owned()).is_none() {
+                reg.add_sound((*name).to_owned(), audio::Source::new(ctx, path).unwrap());
+            }
+        }
+    }
+}
+
+impl States for PlayState {
+    fn name(&self) -> &str {
+        "play"
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        // 레벨 파일이 있다면 그 배치를 사용한다.
+        let world = self.world.as_mut().unwrap();
+        match level_maker::load_level(ctx, world.level) {
+            Some(Ok(level)) => {
+                world.set_blocks(level.blocks);
+                self.level_name = level.name;
+                self.music = level.music;
+                self.background = level.background;
+            }
+            Some(Err(e)) => println!("level file error: {}", e),
+            None => (),
+        }
+
+        self.load_resources(ctx, reg);
+        play_bgm(&"music".to_owned(), reg);
+    }
+
+    /// 게임 도중에 그만두거나 종료해도 점수를 기록한다.
+    /// 다음 state로 넘겨준 게임은 그 state가 기록한다.
+    fn on_exit(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        if let Some(world) = self.world.take() {
+            record_high_score(ctx, world.score, world.level);
+        }
+
+        stop_sound(&("music".to_owned()), reg);
+        clear_resources(reg);
+    }
+
+    /// 일시 정지 화면이 올라오면 음악을 멈춘다.
+    fn on_pause(&mut self, _ctx: &mut Context, reg: &mut Reg) {
+        reg.get_sound_mut("music".to_owned()).unwrap().pause();
+    }
+
+    /// 일시 정지 화면이 닫히면 멈춘 곳부터 음악을 다시 튼다.
+    /// 위의 state가 지운 리소스가 있다면 다시 등록한다.
+    fn on_resume(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+        let music = reg.get_sound_mut("music".to_owned()).unwrap();
+        if music.paused() {
+            music.resume();
+        } else {
+            play_bgm(&"music".to_owned(), reg);
+        }
+    }
+
+    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, dt: f32) -> StateResult {
+        if reg.input.just_pressed(Action::Back) {
+            // 게임을 그만두고 처음 화면으로 돌아간다.
+            StateResult::Reset(Box::new(InitState::new()))
+        } else if reg.input.just_pressed(Action::Pause) {
+            play_sound_once(&("pause".to_owned()), reg);
+
+            // 게임 화면 위에 일시 정지 화면을 띄운다.
+            StateResult::PushState(Box::new(PauseState::new()))
+        } else {
+            let input = PlayState::read_input(reg);
+            let events = self.world.as_mut().unwrap().step(&input, dt);
+            self.play_events(&events, reg);
+            self.particles.update(dt);
+
+            if events.contains(&Event::GameOver) {
+                let world = self.world.take().unwrap();
+                let end_state = EndState::new(world.score, world.level);
+
+                StateResult::Trans(Box::new(end_state))
+            } else if events.contains(&Event::LevelCleared) {
+                // 다음 레벨로 넘길 World는 VictoryState가 가지고 있는다.
+                let world = self.world.take().unwrap();
+                let victory_state = VictoryState::new(world);
+
+                StateResult::Trans(Box::new(victory_state))
+            } else {
+                StateResult::Void
+            }
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
+        graphics::set_canvas(ctx, Some(buffer));
+
+        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
+
+        let world = self.world.as_mut().unwrap();
+
+        // 배경 이미지는 가상 해상도 전체에 맞춰 그린다.
+        if let Some(background) = reg.get_image("background".to_owned()) {
+            let scale = na::Vector2::new(
+                game::VIRTUAL_WIDTH / background.width() as f32,
+                game::VIRTUAL_HEIGHT / background.height() as f32,
+            );
+            graphics::draw(ctx, background, graphics::DrawParam::new().scale(scale)).unwrap();
+        }
+
+        for block in world.blocks().iter() {
+            block.draw(ctx, reg);
+        }
+
+        for power_up in world.power_ups.iter_mut() {
+            power_up.draw(ctx, reg);
+        }
+
+        world.paddle.draw(ctx, reg);
+
+        if let Some(image) = reg.get_image("particle".to_owned()) {
+            self.particles.draw(ctx, image);
+        }
+
+        for ball in world.balls.iter_mut() {
+            ball.draw(ctx, reg);
+        }
+
+        // 서브를 기다리는 중이면 공이 날아갈 방향을 점선으로 보여준다.
+        // 자동 서브가 가까워질수록 점선이 짧아진다.
+        if world.mode == PlayStateMode::READY {
+            if let Some(ball) = world.balls.first() {
+                let (x, y, w, h) = ball.get_xywh();
+                let (cx, cy) = (x + w / 2., y + h / 2.);
+                let (dx, dy) = (world.aim.sin(), -world.aim.cos());
+                let dots =
+                    (SERVE_GUIDE_DOTS as f32 * world.serve_timer / SERVE_TIMEOUT).ceil() as usize;
+
+                for i in 1..=dots {
+                    let distance = i as f32 * SERVE_GUIDE_SPACING;
+                    let dot = graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::fill(),
+                        graphics::Rect::new(
+                            cx + dx * distance - 1.,
+                            cy + dy * distance - 1.,
+                            2.,
+                            2.,
+                        ),
+                        graphics::Color::from_rgba(255, 255, 255, 180),
+                    )
+                    .unwrap();
+                    graphics::draw(ctx, &dot, graphics::DrawParam::new()).unwrap();
+                }
+            }
+        }
+
+        // 생명 출력하기
+        let health = world.health;
+
+        let mut hx = 0.;
+
+        for _ in 0..health {
+            reg.draw_heart(ctx, objects::HEARTS_FLAG, hx, 0.);
+            hx += 11.;
+        }
+
+        for _ in health..MAX_HEALTH {
+            reg.draw_heart(ctx, objects::HEARTS_FLAG + 1, hx, 0.);
+            hx += 11.;
+        }
+
+        // 열쇠를 가지고 있으면 표시한다.
+        if world.has_key {
+            reg.draw_sprite(
+                ctx,
+                objects::POWERUP_FLAG + PowerUpKind::Key.sprite(),
+                hx,
+                0.,
+            );
+        }
+
+        // 레벨 출력하기, 레벨 파일에 이름이 있다면 함께 출력한다.
+        let level = if self.level_name.is_empty() {
+            format!("Level {}", world.level)
+        } else {
+            format!("Level {} - {}", world.level, self.level_name)
+        };
+        let level =
+            ggez::graphics::Text::new((level, *reg.get_font("default".to_owned()).unwrap(), 8.0));
+        let span = level.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            &level,
+            (
+                na::Point2::new((game::VIRTUAL_WIDTH - span) / 2., 0.),
+                0.0,
+                graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        // 점수 출력하기
+        let score = ggez::graphics::Text::new((
+            format!("Score: {}", world.score),
+            *reg.get_font("default".to_owned()).unwrap(),
+            8.0,
+        ));
+        let span = score.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            &score,
+            (
+                na::Point2::new(game::VIRTUAL_WIDTH - span - 4., 0.),
+                0.0,
+                graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        graphics::set_canvas(ctx, None);
+
+        StateResult::Void
+    }
+}
+
+// 레벨 클리어 화면
+pub struct VictoryState {
+    // 다음 레벨로 넘길 게임, PlayState로 넘겨준 뒤에는 None
+    world: Option<World>,
+}
+
+impl VictoryState {
+    pub fn new(world: World) -> VictoryState {
+        VictoryState { world: Some(world) }
+    }
+
+    /// 화면에 쓰는 리소스를 reg에 등록한다.
+    fn load_resources(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        let world = self.world.as_ref().unwrap();
+        let font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
+        let title =
+            ggez::graphics::Text::new((format!("Level {} complete!", world.level), font, 16.0));
+        let score = ggez::graphics::Text::new((format!("Score {}", world.score), font, 12.0));
+        let start_menu = ggez::graphics::Text::new((
+            format!(
+                "Press [{}] To Continue",
+                binding_names(reg, Action::Confirm)
+            ),
+            font,
+            12.0,
+        ));
+
+        reg.add_font("font".to_owned(), font);
+        reg.add_text("title".to_owned(), title);
+        reg.add_text("score".to_owned(), score);
+        reg.add_text("start_menu".to_owned(), start_menu);
+        reg.add_sound(
+            "victory".to_owned(),
+            audio::Source::new(ctx, "/victory.wav").unwrap(),
+        );
+    }
+}
+
+impl States for VictoryState {
+    fn name(&self) -> &str {
+        "victory"
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+        play_sound_once(&"victory".to_owned(), reg);
+    }
+
+    /// 위의 state가 reg를 비웠을 수 있으니 리소스를 다시 등록한다.
+    fn on_resume(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+    }
+
+    /// 다음 레벨로 넘어가지 않고 그만두어도 점수를 기록한다.
+    fn on_exit(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        if let Some(world) = self.world.take() {
+            record_high_score(ctx, world.score, world.level);
+        }
+
+        clear_resources(reg);
+    }
+
+    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
+        if reg.input.just_pressed(Action::Confirm) {
+            // 점수와 생명은 그대로 두고 다음 레벨을 시작한다.
+            let mut world = self.world.take().unwrap();
+            world.next_level();
+            let play_state = PlayState::from_world(world);
+
+            StateResult::Trans(Box::new(play_state))
+        } else {
+            StateResult::Void
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
+        ggez::graphics::set_canvas(ctx, Some(buffer));
+
+        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
+
+        let title = reg.get_text("title".to_owned()).unwrap();
+        let score = reg.get_text("score".to_owned()).unwrap();
+        let start_menu = reg.get_text("start_menu".to_owned()).unwrap();
+
+        let span = title.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            title,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.25,
+                ),
+                0.0,
+                ggez::graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        let span = score.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            score,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.45,
+                ),
+                0.0,
+                ggez::graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        let span = start_menu.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            start_menu,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.85,
+                ),
+                0.0,
+                ggez::graphics::Color::from_rgba(200, 200, 255, 255),
+            ),
+        )
+        .unwrap();
+
+        ggez::graphics::set_canvas(ctx, None);
+        StateResult::Void
+    }
+}
+
+// 게임 종료화면
+pub struct EndState {
+    score: i32,
+    level: i32,
+    // 이번 판을 넣은 최고 점수 기록과 그 순위, 순위 밖이면 None
+    save: Save,
+    rank: Option<usize>,
+}
+
+impl EndState {
+    pub fn new(score: i32, level: i32) -> EndState {
+        EndState {
+            score,
+            level,
+            save: Save::new(),
+            rank: None,
+        }
+    }
+
+    /// 결과 화면에 쓰는 리소스를 reg에 등록한다.
+    fn load_resources(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        let font = ggez::graphics::Font::new(ctx, "/font.ttf").unwrap();
+
+        if self.rank.is_some() {
+            reg.add_sound(
+                "high-score".to_owned(),
+                audio::Source::new(ctx, "/high_score.wav").unwrap(),
+            );
+        }
+
+        let title = match self.rank {
+            Some(_) => format!("New High Score! {} ", self.score),
+            None => format!("Your Score is {} ", self.score),
+        };
+        let title = ggez::graphics::Text::new((title, font, 16.0));
+        let start_menu = ggez::graphics::Text::new((
+            format!("Press [{}] To Return", binding_names(reg, Action::Confirm)),
+            font,
+            12.0,
+        ));
+
+        // 상위 기록표
+        let mut table = String::new();
+        for (i, entry) in self.save.entries.iter().enumerate() {
+            table.push_str(&format!(
+                "{}{:>2}. {:<10} {:>7}  Lv.{:<3} {}\n",
+                if Some(i) == self.rank { ">" } else { " " },
+                i + 1,
+                entry.name,
+                entry.score,
+                entry.level,
+                entry.date
+            ));
+        }
+        let high_scores = ggez::graphics::Text::new((table, font, 8.0));
+
+        reg.add_font("font".to_owned(), font);
+        reg.add_text("title".to_owned(), title);
+        reg.add_text("start_menu".to_owned(), start_menu);
+        reg.add_text("high_scores".to_owned(), high_scores);
+
+        init_global_sprite(reg);
+    }
+}
+
+// 메뉴 화면
+impl States for EndState {
+    fn name(&self) -> &str {
+        "end"
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        let (save, rank) = record_high_score(ctx, self.score, self.level);
+        self.save = save;
+        self.rank = rank;
+
+        self.load_resources(ctx, reg);
+        if self.rank.is_some() {
+            play_sound_once(&"high-score".to_owned(), reg);
+        }
+    }
+
+    /// 위의 state가 reg를 비웠을 수 있으니 리소스를 다시 등록한다.
+    fn on_resume(&mut self, ctx: &mut Context, reg: &mut Reg) {
+        self.load_resources(ctx, reg);
+    }
+
+    fn on_exit(&mut self, _ctx: &mut Context, reg: &mut Reg) {
+        clear_resources(reg);
+    }
+
+    fn update(&mut self, _ctx: &mut Context, reg: &mut Reg, _dt: f32) -> StateResult {
+        if reg.input.just_pressed(Action::Confirm) {
+            StateResult::Reset(Box::new(InitState::new()))
+        } else {
+            StateResult::Void
+        }
+    }
+
+    /// 모든 Render는 이제 자체에 포함된 buffer에만 그린다.
+    fn render(&mut self, ctx: &mut Context, reg: &mut Reg, buffer: &mut Canvas) -> StateResult {
+        ggez::graphics::set_canvas(ctx, Some(buffer));
+
+        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
+
+        // 타이틀 (상단 5%, 각 메뉴 상단에서 85%, 95% 위치)
+        let title = reg.get_text("title".to_owned()).unwrap();
+        let start_menu = reg.get_text("start_menu".to_owned()).unwrap();
+        let high_scores = reg.get_text("high_scores".to_owned()).unwrap();
+        let span = title.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            title,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.05,
+                ),
+                0.0,
+                ggez::graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        let span = high_scores.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            high_scores,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.2,
+                ),
+                0.0,
+                ggez::graphics::WHITE,
+            ),
+        )
+        .unwrap();
+
+        let span = start_menu.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            start_menu,
+            (
+                na::Point2::new(
+                    (game::VIRTUAL_WIDTH - span) / 2.0,
+                    game::VIRTUAL_HEIGHT * 0.85,
+                ),
+                0.0,
+                ggez::graphics::Color::from_rgba(200, 200, 255, 255),
+            ),
+        )
+        .unwrap();
 
         ggez::graphics::set_canvas(ctx, None);
         StateResult::Void